@@ -0,0 +1,271 @@
+mod types;
+
+use ic_cdk_macros::{init, post_upgrade, query, update};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+use crate::types::*;
+
+/// `decimals()`
+const DECIMALS: &str = "0x313ce567";
+/// `latestRoundData()`
+const LATEST_ROUND_DATA: &str = "0xfeaf968c";
+
+/// Labels given to the providers of an `Inconsistent` result, in order.
+const PROVIDERS: [EthMainnetService; 3] = [
+    EthMainnetService::Alchemy,
+    EthMainnetService::Ankr,
+    EthMainnetService::PublicNode,
+];
+
+/// Aggregators scripted on install. The addresses are the mainnet ETH/USD
+/// and BTC/USD aggregators so feeds can be configured as they would be in
+/// production; the answers are made up.
+const ETH_USD_AGGREGATOR: &str = "0x5f4ec3df9cbd43714fe2740f5e3616155c5b8419";
+const BTC_USD_STALE_AGGREGATOR: &str = "0xf4030086522a5beea4988f8ca5b36dbc97bee88c";
+
+#[derive(Default)]
+struct MockState {
+    /// Keyed by lowercase contract address and lowercase `0x`-prefixed selector.
+    calls: BTreeMap<(String, String), ScriptedCall>,
+    failure: Option<RpcError>,
+    stats: MockStats,
+}
+
+thread_local! {
+    static STATE: RefCell<MockState> = RefCell::new(MockState::default());
+}
+
+#[init]
+fn init() {
+    reset();
+}
+
+#[post_upgrade]
+fn post_upgrade() {
+    reset();
+}
+
+// ============================================================================
+// EVM RPC INTERFACE
+// ============================================================================
+
+#[update]
+fn eth_call(_services: RpcServices, _config: Option<RpcConfig>, args: CallArgs) -> MultiCallResult {
+    let available = ic_cdk::api::call::msg_cycles_available128();
+    let accepted = ic_cdk::api::call::msg_cycles_accept128(available);
+    let now_secs = ic_cdk::api::time() / 1_000_000_000;
+
+    STATE.with(|s| {
+        let mut s = s.borrow_mut();
+        s.stats.calls += 1;
+        s.stats.cycles_received += accepted;
+        respond(&s.calls, s.failure.as_ref(), &args.transaction, now_secs)
+    })
+}
+
+/// Builds the response the real canister would give for `transaction`.
+///
+/// Calls nobody scripted return empty data, as calling an address without
+/// code does on a real chain.
+fn respond(
+    calls: &BTreeMap<(String, String), ScriptedCall>,
+    failure: Option<&RpcError>,
+    transaction: &TransactionRequest,
+    now_secs: u64,
+) -> MultiCallResult {
+    if let Some(error) = failure {
+        return MultiCallResult::Consistent(CallResult::Err(error.clone()));
+    }
+
+    let to = transaction.to.clone().unwrap_or_default().to_ascii_lowercase();
+    let input = transaction.input.clone().unwrap_or_default().to_ascii_lowercase();
+    let selector = input.get(..10).unwrap_or(&input).to_string();
+
+    let result = match calls.get(&(to, selector)) {
+        None => CallResult::Ok("0x".to_string()),
+        Some(ScriptedCall::Ok(data)) => CallResult::Ok(data.clone()),
+        Some(ScriptedCall::Err(message)) => CallResult::Err(RpcError::JsonRpcError(JsonRpcError {
+            code: 3,
+            message: format!("execution reverted: {}", message),
+        })),
+        Some(ScriptedCall::RoundData { answer, age_secs }) => {
+            CallResult::Ok(encode_round_data(*answer, now_secs.saturating_sub(*age_secs)))
+        }
+        Some(ScriptedCall::Inconsistent(results)) => {
+            return MultiCallResult::Inconsistent(
+                results
+                    .iter()
+                    .enumerate()
+                    .map(|(i, data)| {
+                        let provider = RpcService::EthMainnet(PROVIDERS[i % PROVIDERS.len()].clone());
+                        (provider, CallResult::Ok(data.clone()))
+                    })
+                    .collect(),
+            )
+        }
+    };
+
+    MultiCallResult::Consistent(result)
+}
+
+/// ABI-encodes `(roundId, answer, startedAt, updatedAt, answeredInRound)`.
+fn encode_round_data(answer: i64, updated_at: u64) -> String {
+    let round_id = 110_680_464_442_257_320_000u128;
+    let answer_word = {
+        let extension = if answer < 0 { [0xff; 24] } else { [0; 24] };
+        [extension.as_slice(), &answer.to_be_bytes()].concat()
+    };
+
+    let words = [
+        uint_word(round_id),
+        answer_word,
+        uint_word(updated_at as u128),
+        uint_word(updated_at as u128),
+        uint_word(round_id),
+    ];
+    format!("0x{}", hex::encode(words.concat()))
+}
+
+fn uint_word(value: u128) -> Vec<u8> {
+    [[0u8; 16].as_slice(), &value.to_be_bytes()].concat()
+}
+
+fn uint8_result(value: u8) -> String {
+    format!("0x{}", hex::encode(uint_word(value as u128)))
+}
+
+fn key(to: &str, selector: &str) -> (String, String) {
+    (to.to_ascii_lowercase(), selector.to_ascii_lowercase())
+}
+
+// ============================================================================
+// SCRIPTING
+// ============================================================================
+
+/// Restores the scripted aggregators, clears any injected failure and zeroes
+/// the stats.
+///
+/// Both aggregators report 8 decimals. ETH/USD answers $2623.45678901
+/// updated a minute before each call; BTC/USD answers $67,345.12345678 but
+/// was last updated two hours before each call, so the feeder should reject
+/// it as stale.
+#[update]
+fn reset() {
+    let calls = BTreeMap::from([
+        (key(ETH_USD_AGGREGATOR, DECIMALS), ScriptedCall::Ok(uint8_result(8))),
+        (
+            key(ETH_USD_AGGREGATOR, LATEST_ROUND_DATA),
+            ScriptedCall::RoundData { answer: 262_345_678_901, age_secs: 60 },
+        ),
+        (key(BTC_USD_STALE_AGGREGATOR, DECIMALS), ScriptedCall::Ok(uint8_result(8))),
+        (
+            key(BTC_USD_STALE_AGGREGATOR, LATEST_ROUND_DATA),
+            ScriptedCall::RoundData { answer: 6_734_512_345_678, age_secs: 7_200 },
+        ),
+    ]);
+
+    STATE.with(|s| {
+        *s.borrow_mut() = MockState { calls, ..MockState::default() };
+    });
+}
+
+/// Scripts calls to `selector` (e.g. `0xfeaf968c`) on contract `to`.
+#[update]
+fn set_call(to: String, selector: String, call: ScriptedCall) {
+    STATE.with(|s| s.borrow_mut().calls.insert(key(&to, &selector), call));
+}
+
+#[update]
+fn remove_call(to: String, selector: String) -> bool {
+    STATE.with(|s| s.borrow_mut().calls.remove(&key(&to, &selector)).is_some())
+}
+
+/// Makes every call fail with `error` until cleared with `null`.
+#[update]
+fn set_failure(error: Option<RpcError>) {
+    STATE.with(|s| s.borrow_mut().failure = error);
+}
+
+#[query]
+fn get_calls() -> Vec<(String, String, ScriptedCall)> {
+    STATE.with(|s| {
+        s.borrow()
+            .calls
+            .iter()
+            .map(|((to, selector), call)| (to.clone(), selector.clone(), call.clone()))
+            .collect()
+    })
+}
+
+#[query]
+fn get_stats() -> MockStats {
+    STATE.with(|s| s.borrow().stats.clone())
+}
+
+ic_cdk::export_candid!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transaction(to: &str, input: &str) -> TransactionRequest {
+        TransactionRequest {
+            to: Some(to.to_string()),
+            input: Some(input.to_string()),
+        }
+    }
+
+    fn calls() -> BTreeMap<(String, String), ScriptedCall> {
+        BTreeMap::from([
+            (key("0xAA", LATEST_ROUND_DATA), ScriptedCall::RoundData { answer: -2, age_secs: 10 }),
+            (key("0xaa", DECIMALS), ScriptedCall::Err("no".to_string())),
+            (key("0xbb", DECIMALS), ScriptedCall::Inconsistent(vec!["0x01".to_string(), "0x02".to_string()])),
+        ])
+    }
+
+    #[test]
+    fn test_round_data_is_dated_relative_to_the_call() {
+        let MultiCallResult::Consistent(CallResult::Ok(data)) =
+            respond(&calls(), None, &transaction("0xaa", "0xFEAF968C"), 1_000)
+        else {
+            panic!("expected round data");
+        };
+
+        let bytes = hex::decode(&data[2..]).unwrap();
+        assert_eq!(bytes.len(), 160);
+        assert_eq!(bytes[32..64], [[0xff; 31].as_slice(), &[0xfe]].concat());
+        assert_eq!(u128::from_be_bytes(bytes[112..128].try_into().unwrap()), 990);
+    }
+
+    #[test]
+    fn test_scripted_errors_and_disagreement() {
+        assert!(matches!(
+            respond(&calls(), None, &transaction("0xAA", DECIMALS), 0),
+            MultiCallResult::Consistent(CallResult::Err(RpcError::JsonRpcError(_)))
+        ));
+
+        let MultiCallResult::Inconsistent(results) = respond(&calls(), None, &transaction("0xbb", DECIMALS), 0) else {
+            panic!("expected an inconsistent result");
+        };
+        assert_eq!(results[1], (RpcService::EthMainnet(EthMainnetService::Ankr), CallResult::Ok("0x02".to_string())));
+    }
+
+    #[test]
+    fn test_unscripted_call_returns_empty_data() {
+        assert_eq!(
+            respond(&calls(), None, &transaction("0xcc", DECIMALS), 0),
+            MultiCallResult::Consistent(CallResult::Ok("0x".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_injected_failure_wins() {
+        let error = RpcError::ValidationError("down".to_string());
+
+        assert_eq!(
+            respond(&calls(), Some(&error), &transaction("0xaa", DECIMALS), 0),
+            MultiCallResult::Consistent(CallResult::Err(error))
+        );
+    }
+}
@@ -0,0 +1,114 @@
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+/// What the mock returns for one (contract, selector) pair.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum ScriptedCall {
+    /// Every provider returns this `0x`-prefixed return data.
+    Ok(String),
+    /// Every provider reports the call as reverted with this message.
+    Err(String),
+    /// A `latestRoundData()` answer updated `age_secs` before the call.
+    RoundData { answer: i64, age_secs: u64 },
+    /// Provider `i` returns entry `i`, which makes the result `Inconsistent`.
+    Inconsistent(Vec<String>),
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Default)]
+pub struct MockStats {
+    pub calls: u64,
+    pub cycles_received: u128,
+}
+
+// The request and response types below mirror the subset of the EVM RPC
+// canister interface the feeder uses.
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum RpcServices {
+    EthMainnet(Option<Vec<EthMainnetService>>),
+    ArbitrumOne(Option<Vec<L2MainnetService>>),
+    BaseMainnet(Option<Vec<L2MainnetService>>),
+    OptimismMainnet(Option<Vec<L2MainnetService>>),
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum EthMainnetService {
+    Alchemy,
+    Ankr,
+    BlockPi,
+    PublicNode,
+    Cloudflare,
+    Llama,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum L2MainnetService {
+    Alchemy,
+    Ankr,
+    BlockPi,
+    PublicNode,
+    Llama,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum RpcService {
+    EthMainnet(EthMainnetService),
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum ConsensusStrategy {
+    Equality,
+    Threshold { min: u8, total: Option<u8> },
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RpcConfig {
+    #[serde(rename = "responseSizeEstimate")]
+    pub response_size_estimate: Option<u64>,
+    #[serde(rename = "responseConsensus")]
+    pub response_consensus: Option<ConsensusStrategy>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum BlockTag {
+    Latest,
+    Safe,
+    Finalized,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TransactionRequest {
+    pub to: Option<String>,
+    pub input: Option<String>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CallArgs {
+    pub transaction: TransactionRequest,
+    pub block: Option<BlockTag>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum MultiCallResult {
+    Consistent(CallResult),
+    Inconsistent(Vec<(RpcService, CallResult)>),
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum CallResult {
+    Ok(String),
+    Err(RpcError),
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum RpcError {
+    JsonRpcError(JsonRpcError),
+    ValidationError(String),
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct JsonRpcError {
+    pub code: i64,
+    pub message: String,
+}
@@ -0,0 +1,214 @@
+mod types;
+
+use ic_cdk_macros::{init, post_upgrade, query, update};
+use serde_bytes::ByteBuf;
+use serde_json::{json, Value};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+use crate::types::*;
+
+/// Prices loaded on install. The data comes from the feeder's own Hermes
+/// parsing fixture.
+const FIXTURE: &str = include_str!("../pyth_feeder/fixtures/hermes_latest.json");
+
+const PRICE_PATH: &str = "/v2/updates/price/";
+
+#[derive(Default)]
+struct MockState {
+    /// Keyed by lowercase hex feed id without the `0x` prefix.
+    prices: BTreeMap<String, ScriptedPrice>,
+    failure: Option<u16>,
+}
+
+thread_local! {
+    static STATE: RefCell<MockState> = RefCell::new(MockState::default());
+}
+
+#[init]
+fn init() {
+    reset();
+}
+
+#[post_upgrade]
+fn post_upgrade() {
+    reset();
+}
+
+// ============================================================================
+// HERMES INTERFACE
+// ============================================================================
+
+/// Serves `GET /v2/updates/price/{publish_time}?ids[]=...` the way Hermes
+/// does with `parsed=true` and `ignore_invalid_price_ids=true`: one `parsed`
+/// entry per scripted id, in request order, and nothing for unknown ids.
+///
+/// The updates are not signed, so there is no `binary` section and feeders
+/// pointed here must run without `hermes.guardian_set`.
+#[query]
+fn http_request(request: HttpRequest) -> HttpResponse {
+    STATE.with(|s| {
+        let s = s.borrow();
+        match s.failure {
+            Some(status) => text_response(status, "Scripted failure"),
+            None => respond(&s.prices, &request.url),
+        }
+    })
+}
+
+fn respond(prices: &BTreeMap<String, ScriptedPrice>, url: &str) -> HttpResponse {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let Some(publish_time) = path.strip_prefix(PRICE_PATH).and_then(|t| t.parse::<u64>().ok()) else {
+        return text_response(404, "Not found");
+    };
+
+    let parsed: Vec<Value> = query
+        .split('&')
+        .filter_map(|pair| pair.strip_prefix("ids[]=").or_else(|| pair.strip_prefix("ids%5B%5D=")))
+        .map(normalize_feed_id)
+        .filter_map(|id| {
+            let price = prices.get(&id)?;
+            let publish_time = price.publish_time.unwrap_or(publish_time);
+            Some(json!({
+                "id": id,
+                "price": {
+                    "price": price.price.to_string(),
+                    "conf": price.conf.to_string(),
+                    "expo": price.expo,
+                    "publish_time": publish_time,
+                },
+            }))
+        })
+        .collect();
+
+    HttpResponse {
+        status_code: 200,
+        headers: vec![("Content-Type".to_string(), "application/json".to_string())],
+        body: ByteBuf::from(json!({ "parsed": parsed }).to_string()),
+    }
+}
+
+fn text_response(status_code: u16, body: &str) -> HttpResponse {
+    HttpResponse {
+        status_code,
+        headers: vec![("Content-Type".to_string(), "text/plain".to_string())],
+        body: ByteBuf::from(body.as_bytes()),
+    }
+}
+
+fn normalize_feed_id(feed_id: &str) -> String {
+    feed_id.trim().trim_start_matches("0x").to_ascii_lowercase()
+}
+
+/// The fixture's `parsed` prices, served at whatever time is requested.
+fn fixture_prices() -> BTreeMap<String, ScriptedPrice> {
+    let fixture: Value = serde_json::from_str(FIXTURE).expect("Hermes fixture is JSON");
+    fixture["parsed"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|update| {
+            let price = ScriptedPrice {
+                price: price_field(update, "price")?,
+                conf: price_field(update, "conf")?,
+                expo: update["price"]["expo"].as_i64()? as i32,
+                publish_time: None,
+            };
+            Some((normalize_feed_id(update["id"].as_str()?), price))
+        })
+        .collect()
+}
+
+/// Hermes sends prices and confidences as decimal strings.
+fn price_field<T: std::str::FromStr>(update: &Value, name: &str) -> Option<T> {
+    update["price"][name].as_str()?.parse().ok()
+}
+
+// ============================================================================
+// SCRIPTING
+// ============================================================================
+
+/// Restores the fixture prices and clears any injected failure.
+#[update]
+fn reset() {
+    STATE.with(|s| {
+        *s.borrow_mut() = MockState { prices: fixture_prices(), failure: None };
+    });
+}
+
+#[update]
+fn set_price(feed_id: String, price: ScriptedPrice) {
+    STATE.with(|s| s.borrow_mut().prices.insert(normalize_feed_id(&feed_id), price));
+}
+
+#[update]
+fn remove_price(feed_id: String) -> bool {
+    STATE.with(|s| s.borrow_mut().prices.remove(&normalize_feed_id(&feed_id)).is_some())
+}
+
+/// Answers every request with this HTTP status until cleared with `null`.
+#[update]
+fn set_failure(status: Option<u16>) {
+    STATE.with(|s| s.borrow_mut().failure = status);
+}
+
+#[query]
+fn get_prices() -> Vec<(String, ScriptedPrice)> {
+    STATE.with(|s| s.borrow().prices.clone().into_iter().collect())
+}
+
+ic_cdk::export_candid!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BTC: &str = "e62df6c8b4a85fe1a67db44dc12de5db330f7ac66b72dc658afedf0f4a415b43";
+
+    fn body(response: &HttpResponse) -> Value {
+        serde_json::from_slice(&response.body).unwrap()
+    }
+
+    #[test]
+    fn test_fixture_prices_load() {
+        let prices = fixture_prices();
+        assert_eq!(prices.len(), 2);
+        assert_eq!(prices[BTC].price, 6_734_512_345_678);
+        assert_eq!((prices[BTC].conf, prices[BTC].expo), (2_150_000_000, -8));
+    }
+
+    #[test]
+    fn test_serves_known_ids_at_the_requested_time() {
+        let prices = fixture_prices();
+        let url = format!(
+            "/v2/updates/price/1728000100?ids[]=0x{}&ids%5B%5D=ffff&parsed=true&encoding=hex",
+            BTC.to_uppercase()
+        );
+
+        let response = respond(&prices, &url);
+        assert_eq!(response.status_code, 200);
+        let parsed = body(&response)["parsed"].as_array().unwrap().clone();
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0]["id"], BTC);
+        assert_eq!(parsed[0]["price"]["price"], "6734512345678");
+        assert_eq!(parsed[0]["price"]["publish_time"], 1_728_000_100);
+    }
+
+    #[test]
+    fn test_scripted_publish_time_overrides_the_request() {
+        let prices = BTreeMap::from([(
+            BTC.to_string(),
+            ScriptedPrice { price: 1, conf: 0, expo: 0, publish_time: Some(42) },
+        )]);
+
+        let response = respond(&prices, &format!("/v2/updates/price/1728000100?ids[]={}", BTC));
+        assert_eq!(body(&response)["parsed"][0]["price"]["publish_time"], 42);
+    }
+
+    #[test]
+    fn test_other_paths_are_not_found() {
+        for url in ["/v2/updates/price/latest?ids[]=00", "/api/latest_price_feeds", "/"] {
+            assert_eq!(respond(&BTreeMap::new(), url).status_code, 404, "{}", url);
+        }
+    }
+}
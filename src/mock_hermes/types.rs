@@ -0,0 +1,32 @@
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+use serde_bytes::ByteBuf;
+
+/// The price the mock serves for one feed id.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ScriptedPrice {
+    pub price: i64,
+    pub conf: u64,
+    pub expo: i32,
+    /// Reported instead of the requested publish time, e.g. to serve a price
+    /// older than the feeder asked for.
+    pub publish_time: Option<u64>,
+}
+
+// The request and response types below are the HTTP gateway's interface for
+// canisters that serve HTTP.
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct HttpRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: ByteBuf,
+}
+
+#[derive(CandidType, Serialize, Clone, Debug, PartialEq)]
+pub struct HttpResponse {
+    pub status_code: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: ByteBuf,
+}
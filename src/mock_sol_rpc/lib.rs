@@ -0,0 +1,340 @@
+mod types;
+
+use ic_cdk_macros::{init, post_upgrade, query, update};
+use serde_bytes::ByteBuf;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+use crate::types::*;
+
+const PYTH_ORACLE_PROGRAM: &str = "FsJ3A3u2vn5cTVofAjvy6y5kwABJAqYWpe4975bi2epH";
+const PYTH_RECEIVER_PROGRAM: &str = "rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ";
+const SWITCHBOARD_PROGRAM: &str = "SBondMDrcV3K4kxZR1HNVT7osZxAHVHgYXL5Ze1oMUv";
+
+/// Reported by `getSlot` until scripted otherwise: a few slots after the
+/// newest fixture was updated.
+const DEFAULT_SLOT: u64 = 289_345_140;
+
+/// Labels given to the providers of an `Inconsistent` result, in order.
+const PROVIDERS: [SupportedRpcProviderId; 5] = [
+    SupportedRpcProviderId::AlchemyMainnet,
+    SupportedRpcProviderId::AnkrMainnet,
+    SupportedRpcProviderId::HeliusMainnet,
+    SupportedRpcProviderId::DrpcMainnet,
+    SupportedRpcProviderId::PublicNodeMainnet,
+];
+
+/// Accounts loaded on install, keyed by the pubkey the feeder should use.
+/// The data comes from the feeder's own decoding fixtures.
+const FIXTURES: [(&str, &str); 6] = [
+    ("btc_usd_legacy", include_str!("../pyth_feeder/fixtures/btc_usd_legacy.b64")),
+    ("aapl_usd_legacy_halted", include_str!("../pyth_feeder/fixtures/aapl_usd_legacy_halted.b64")),
+    ("sol_usd_price_update_v2_full", include_str!("../pyth_feeder/fixtures/sol_usd_price_update_v2_full.b64")),
+    ("eth_usd_price_update_v2_partial", include_str!("../pyth_feeder/fixtures/eth_usd_price_update_v2_partial.b64")),
+    ("btc_usd_product", include_str!("../pyth_feeder/fixtures/btc_usd_product.b64")),
+    ("sol_usd_switchboard_pull_feed", include_str!("../pyth_feeder/fixtures/sol_usd_switchboard_pull_feed.b64")),
+];
+
+#[derive(Default)]
+struct MockState {
+    accounts: BTreeMap<String, ScriptedAccount>,
+    slot: u64,
+    failure: Option<RpcError>,
+    stats: MockStats,
+}
+
+thread_local! {
+    static STATE: RefCell<MockState> = RefCell::new(MockState::default());
+}
+
+#[init]
+fn init() {
+    reset();
+}
+
+#[post_upgrade]
+fn post_upgrade() {
+    reset();
+}
+
+// ============================================================================
+// SOL RPC INTERFACE
+// ============================================================================
+
+#[update(name = "getMultipleAccounts")]
+fn get_multiple_accounts(
+    _sources: RpcSources,
+    _config: Option<RpcConfig>,
+    params: GetMultipleAccountsParams,
+) -> MultiGetMultipleAccountsResult {
+    accept_call(params.pubkeys.len());
+
+    STATE.with(|s| {
+        let s = s.borrow();
+        respond(&s.accounts, s.failure.as_ref(), &params.pubkeys)
+    })
+}
+
+#[update(name = "getAccountInfo")]
+fn get_account_info(
+    _sources: RpcSources,
+    _config: Option<RpcConfig>,
+    params: GetAccountInfoParams,
+) -> MultiGetAccountInfoResult {
+    accept_call(1);
+
+    let single = |result: GetMultipleAccountsResult| match result {
+        GetMultipleAccountsResult::Ok(mut accounts) => GetAccountInfoResult::Ok(accounts.remove(0)),
+        GetMultipleAccountsResult::Err(e) => GetAccountInfoResult::Err(e),
+    };
+
+    let result = STATE.with(|s| {
+        let s = s.borrow();
+        respond(&s.accounts, s.failure.as_ref(), &[params.pubkey])
+    });
+
+    match result {
+        MultiGetMultipleAccountsResult::Consistent(result) => {
+            MultiGetAccountInfoResult::Consistent(single(result))
+        }
+        MultiGetMultipleAccountsResult::Inconsistent(results) => MultiGetAccountInfoResult::Inconsistent(
+            results.into_iter().map(|(source, result)| (source, single(result))).collect(),
+        ),
+    }
+}
+
+#[update(name = "getSlot")]
+fn get_slot(_sources: RpcSources, _config: Option<GetSlotRpcConfig>, _params: Option<GetSlotParams>) -> MultiGetSlotResult {
+    accept_call(0);
+
+    STATE.with(|s| {
+        let s = s.borrow();
+        MultiGetSlotResult::Consistent(match &s.failure {
+            Some(error) => GetSlotResult::Err(error.clone()),
+            None => GetSlotResult::Ok(s.slot),
+        })
+    })
+}
+
+fn accept_call(accounts: usize) {
+    let available = ic_cdk::api::call::msg_cycles_available128();
+    let accepted = ic_cdk::api::call::msg_cycles_accept128(available);
+
+    STATE.with(|s| {
+        let stats = &mut s.borrow_mut().stats;
+        stats.calls += 1;
+        stats.accounts_requested += accounts as u64;
+        stats.cycles_received += accepted;
+    });
+}
+
+/// Builds the response the real canister would give for `pubkeys`.
+///
+/// Unknown keys are reported as not found. If any requested key is scripted
+/// as `Inconsistent`, the whole response is, with one entry per provider.
+fn respond(
+    accounts: &BTreeMap<String, ScriptedAccount>,
+    failure: Option<&RpcError>,
+    pubkeys: &[String],
+) -> MultiGetMultipleAccountsResult {
+    if let Some(error) = failure {
+        return MultiGetMultipleAccountsResult::Consistent(GetMultipleAccountsResult::Err(error.clone()));
+    }
+
+    let providers = pubkeys
+        .iter()
+        .filter_map(|key| match accounts.get(key) {
+            Some(ScriptedAccount::Inconsistent(views)) => Some(views.len()),
+            _ => None,
+        })
+        .max();
+
+    let view = |provider: usize| -> Vec<Option<AccountInfo>> {
+        pubkeys
+            .iter()
+            .map(|key| match accounts.get(key) {
+                Some(ScriptedAccount::Data(bytes)) => Some(account_info(bytes)),
+                Some(ScriptedAccount::Inconsistent(views)) => {
+                    views.get(provider).cloned().flatten().map(|bytes| account_info(&bytes))
+                }
+                Some(ScriptedAccount::Missing) | None => None,
+            })
+            .collect()
+    };
+
+    match providers {
+        None => MultiGetMultipleAccountsResult::Consistent(GetMultipleAccountsResult::Ok(view(0))),
+        Some(providers) => MultiGetMultipleAccountsResult::Inconsistent(
+            (0..providers)
+                .map(|provider| {
+                    let source = RpcSource::Supported(PROVIDERS[provider % PROVIDERS.len()].clone());
+                    (source, GetMultipleAccountsResult::Ok(view(provider)))
+                })
+                .collect(),
+        ),
+    }
+}
+
+fn account_info(bytes: &[u8]) -> AccountInfo {
+    use base64::Engine;
+
+    let owner = if bytes.starts_with(&[34, 241, 35, 99, 157, 126, 244, 205]) {
+        PYTH_RECEIVER_PROGRAM
+    } else if bytes.starts_with(&[196, 27, 108, 196, 10, 215, 219, 40]) {
+        SWITCHBOARD_PROGRAM
+    } else {
+        PYTH_ORACLE_PROGRAM
+    };
+
+    AccountInfo {
+        lamports: 1_000_000,
+        data: AccountData::Binary((
+            base64::engine::general_purpose::STANDARD.encode(bytes),
+            AccountEncoding::Base64,
+        )),
+        owner: owner.to_string(),
+        executable: false,
+        rent_epoch: u64::MAX,
+        space: bytes.len() as u64,
+    }
+}
+
+fn fixture(name: &str) -> Option<ByteBuf> {
+    use base64::Engine;
+
+    FIXTURES
+        .iter()
+        .find(|(key, _)| *key == name)
+        .and_then(|(_, encoded)| base64::engine::general_purpose::STANDARD.decode(encoded.trim()).ok())
+        .map(ByteBuf::from)
+}
+
+// ============================================================================
+// SCRIPTING
+// ============================================================================
+
+/// Restores the fixture accounts and the default slot, clears any injected
+/// failure and zeroes the stats.
+///
+/// Besides the fixtures, `btc_usd_disputed` makes every request that includes
+/// it `Inconsistent`, with two of three providers agreeing on the BTC price.
+#[update]
+fn reset() {
+    let mut accounts: BTreeMap<String, ScriptedAccount> = FIXTURES
+        .iter()
+        .filter_map(|(key, _)| fixture(key).map(|bytes| (key.to_string(), ScriptedAccount::Data(bytes))))
+        .collect();
+
+    accounts.insert(
+        "btc_usd_disputed".to_string(),
+        ScriptedAccount::Inconsistent(vec![
+            fixture("btc_usd_legacy"),
+            fixture("btc_usd_legacy"),
+            fixture("aapl_usd_legacy_halted"),
+        ]),
+    );
+
+    STATE.with(|s| {
+        *s.borrow_mut() = MockState { accounts, slot: DEFAULT_SLOT, ..MockState::default() };
+    });
+}
+
+#[update]
+fn set_account(pubkey: String, account: ScriptedAccount) {
+    STATE.with(|s| s.borrow_mut().accounts.insert(pubkey, account));
+}
+
+#[update]
+fn remove_account(pubkey: String) -> bool {
+    STATE.with(|s| s.borrow_mut().accounts.remove(&pubkey).is_some())
+}
+
+/// Sets the slot `getSlot` reports, to make fixture slots look stale.
+#[update]
+fn set_slot(slot: u64) {
+    STATE.with(|s| s.borrow_mut().slot = slot);
+}
+
+/// Makes every call fail with `error` until cleared with `null`.
+#[update]
+fn set_failure(error: Option<RpcError>) {
+    STATE.with(|s| s.borrow_mut().failure = error);
+}
+
+#[query]
+fn get_accounts() -> Vec<(String, ScriptedAccount)> {
+    STATE.with(|s| s.borrow().accounts.clone().into_iter().collect())
+}
+
+#[query]
+fn get_stats() -> MockStats {
+    STATE.with(|s| s.borrow().stats.clone())
+}
+
+ic_cdk::export_candid!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn accounts() -> BTreeMap<String, ScriptedAccount> {
+        BTreeMap::from([
+            ("a".to_string(), ScriptedAccount::Data(ByteBuf::from(vec![1]))),
+            ("gone".to_string(), ScriptedAccount::Missing),
+            (
+                "split".to_string(),
+                ScriptedAccount::Inconsistent(vec![Some(ByteBuf::from(vec![2])), None]),
+            ),
+        ])
+    }
+
+    fn keys(keys: &[&str]) -> Vec<String> {
+        keys.iter().map(|k| k.to_string()).collect()
+    }
+
+    #[test]
+    fn test_fixtures_decode() {
+        for (name, _) in FIXTURES {
+            assert!(fixture(name).is_some(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_consistent_response_keeps_request_order() {
+        let MultiGetMultipleAccountsResult::Consistent(GetMultipleAccountsResult::Ok(result)) =
+            respond(&accounts(), None, &keys(&["unknown", "a", "gone"]))
+        else {
+            panic!("expected a consistent result");
+        };
+
+        assert_eq!(result.len(), 3);
+        assert!(result[0].is_none() && result[2].is_none());
+        assert_eq!(result[1], Some(account_info(&[1])));
+    }
+
+    #[test]
+    fn test_inconsistent_key_splits_the_whole_response() {
+        let MultiGetMultipleAccountsResult::Inconsistent(results) =
+            respond(&accounts(), None, &keys(&["a", "split"]))
+        else {
+            panic!("expected an inconsistent result");
+        };
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(
+            results[0].1,
+            GetMultipleAccountsResult::Ok(vec![Some(account_info(&[1])), Some(account_info(&[2]))])
+        );
+        assert_eq!(results[1].1, GetMultipleAccountsResult::Ok(vec![Some(account_info(&[1])), None]));
+    }
+
+    #[test]
+    fn test_injected_failure_wins() {
+        let error = RpcError::ValidationError("down".to_string());
+
+        assert_eq!(
+            respond(&accounts(), Some(&error), &keys(&["a"])),
+            MultiGetMultipleAccountsResult::Consistent(GetMultipleAccountsResult::Err(error))
+        );
+    }
+}
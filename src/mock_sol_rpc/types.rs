@@ -0,0 +1,227 @@
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+use serde_bytes::ByteBuf;
+
+/// What the mock returns for one public key.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum ScriptedAccount {
+    /// Every provider returns these bytes.
+    Data(ByteBuf),
+    /// Every provider reports the account as not found.
+    Missing,
+    /// Provider `i` returns entry `i` (`null` meaning not found), which turns
+    /// any request touching this key into an `Inconsistent` result.
+    Inconsistent(Vec<Option<ByteBuf>>),
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Default)]
+pub struct MockStats {
+    pub calls: u64,
+    pub accounts_requested: u64,
+    pub cycles_received: u128,
+}
+
+// The request and response types below mirror the subset of the SOL RPC
+// canister interface the feeder uses.
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum RpcSources {
+    Custom(Vec<RpcSource>),
+    Default(SolanaCluster),
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum SolanaCluster {
+    Mainnet,
+    Devnet,
+    Testnet,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum RpcSource {
+    Supported(SupportedRpcProviderId),
+    Custom(RpcEndpoint),
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum SupportedRpcProviderId {
+    AlchemyMainnet,
+    AlchemyDevnet,
+    AnkrMainnet,
+    AnkrDevnet,
+    DrpcMainnet,
+    DrpcDevnet,
+    HeliusMainnet,
+    HeliusDevnet,
+    PublicNodeMainnet,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RpcEndpoint {
+    pub url: String,
+    pub headers: Option<Vec<HttpHeader>>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HttpHeader {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub enum CommitmentLevel {
+    #[serde(rename = "processed")]
+    Processed,
+    #[serde(rename = "confirmed")]
+    Confirmed,
+    #[serde(rename = "finalized")]
+    Finalized,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub enum ConsensusStrategy {
+    Equality,
+    Threshold { min: u8, total: Option<u8> },
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct RpcConfig {
+    #[serde(rename = "responseSizeEstimate")]
+    pub response_size_estimate: Option<u64>,
+    #[serde(rename = "responseConsensus")]
+    pub response_consensus: Option<ConsensusStrategy>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub enum GetAccountInfoEncoding {
+    #[serde(rename = "base58")]
+    Base58,
+    #[serde(rename = "base64")]
+    Base64,
+    #[serde(rename = "base64+zstd")]
+    Base64Zstd,
+    #[serde(rename = "jsonParsed")]
+    JsonParsed,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct GetAccountInfoParams {
+    pub pubkey: String,
+    pub commitment: Option<CommitmentLevel>,
+    pub encoding: Option<GetAccountInfoEncoding>,
+    #[serde(rename = "dataSlice")]
+    pub data_slice: Option<()>,
+    #[serde(rename = "minContextSlot")]
+    pub min_context_slot: Option<u64>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct GetMultipleAccountsParams {
+    pub pubkeys: Vec<String>,
+    pub commitment: Option<CommitmentLevel>,
+    pub encoding: Option<GetAccountInfoEncoding>,
+    #[serde(rename = "dataSlice")]
+    pub data_slice: Option<()>,
+    #[serde(rename = "minContextSlot")]
+    pub min_context_slot: Option<u64>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct GetSlotRpcConfig {
+    #[serde(rename = "responseSizeEstimate")]
+    pub response_size_estimate: Option<u64>,
+    #[serde(rename = "responseConsensus")]
+    pub response_consensus: Option<ConsensusStrategy>,
+    #[serde(rename = "roundingError")]
+    pub rounding_error: Option<u64>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct GetSlotParams {
+    pub commitment: Option<CommitmentLevel>,
+    #[serde(rename = "minContextSlot")]
+    pub min_context_slot: Option<u64>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum MultiGetSlotResult {
+    Consistent(GetSlotResult),
+    Inconsistent(Vec<(RpcSource, GetSlotResult)>),
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum GetSlotResult {
+    Ok(u64),
+    Err(RpcError),
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum MultiGetAccountInfoResult {
+    Consistent(GetAccountInfoResult),
+    Inconsistent(Vec<(RpcSource, GetAccountInfoResult)>),
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum GetAccountInfoResult {
+    Ok(Option<AccountInfo>),
+    Err(RpcError),
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum MultiGetMultipleAccountsResult {
+    Consistent(GetMultipleAccountsResult),
+    Inconsistent(Vec<(RpcSource, GetMultipleAccountsResult)>),
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum GetMultipleAccountsResult {
+    Ok(Vec<Option<AccountInfo>>),
+    Err(RpcError),
+}
+
+/// The error variants a script can inject. The real canister has more, all
+/// of which the feeder treats the same way.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum RpcError {
+    JsonRpcError(JsonRpcError),
+    ValidationError(String),
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct JsonRpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AccountInfo {
+    pub lamports: u64,
+    pub data: AccountData,
+    pub owner: String,
+    pub executable: bool,
+    #[serde(rename = "rentEpoch")]
+    pub rent_epoch: u64,
+    pub space: u64,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum AccountData {
+    #[serde(rename = "legacyBinary")]
+    LegacyBinary(String),
+    #[serde(rename = "binary")]
+    Binary((String, AccountEncoding)),
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum AccountEncoding {
+    #[serde(rename = "binary")]
+    Binary,
+    #[serde(rename = "base58")]
+    Base58,
+    #[serde(rename = "base64")]
+    Base64,
+    #[serde(rename = "base64+zstd")]
+    Base64Zstd,
+    #[serde(rename = "jsonParsed")]
+    JsonParsed,
+}
@@ -0,0 +1,180 @@
+use crate::types::AggregationSettings;
+
+/// The oracle rejects sources longer than this.
+const MAX_SOURCE_LEN: usize = 100;
+
+/// One source's price for an oracle symbol, in the symbol's decimals.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Component {
+    pub source: String,
+    pub value: i64,
+    pub confidence: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Aggregate {
+    pub value: i64,
+    pub confidence: u64,
+    /// The `source` to push: a lone source's own name, otherwise
+    /// `median(a,b,c)` over the sources kept.
+    pub source: String,
+    pub kept: Vec<String>,
+    pub dropped: Vec<String>,
+    /// Spread between the highest and lowest component, in basis points of
+    /// the median of all components.
+    pub spread_bps: u64,
+    pub diverged: bool,
+}
+
+/// Combines the components for one symbol into a single price.
+///
+/// Components further than `max_outlier_bps` from the median of all of them
+/// are dropped and the median is taken again over the rest, which must
+/// number at least `min_sources`. The aggregate's confidence is the larger of
+/// the kept components' median confidence and half their range.
+pub fn aggregate(components: &[Component], settings: &AggregationSettings) -> Result<Aggregate, String> {
+    if components.is_empty() {
+        return Err("No sources".to_string());
+    }
+
+    let values: Vec<i64> = components.iter().map(|c| c.value).collect();
+    let center = median(&values);
+    let min = *values.iter().min().expect("not empty");
+    let max = *values.iter().max().expect("not empty");
+    let spread_bps = bps(max as i128 - min as i128, center);
+
+    let (kept, dropped): (Vec<&Component>, Vec<&Component>) = components
+        .iter()
+        .partition(|c| bps(c.value as i128 - center as i128, center) <= settings.max_outlier_bps as u64);
+    let dropped: Vec<String> = dropped.iter().map(|c| c.source.clone()).collect();
+
+    if kept.len() < settings.min_sources.max(1) as usize {
+        return Err(format!(
+            "{} of {} sources agree within {} bps (min {}); dropped {}",
+            kept.len(),
+            components.len(),
+            settings.max_outlier_bps,
+            settings.min_sources,
+            dropped.join(",")
+        ));
+    }
+
+    let kept_values: Vec<i64> = kept.iter().map(|c| c.value).collect();
+    let value = median(&kept_values);
+    let confidences: Vec<i64> = kept.iter().map(|c| c.confidence.min(i64::MAX as u64) as i64).collect();
+    let half_range = (*kept_values.iter().max().expect("not empty") as i128
+        - *kept_values.iter().min().expect("not empty") as i128)
+        / 2;
+    let confidence = (median(&confidences) as i128).max(half_range) as u64;
+
+    let kept: Vec<String> = kept.iter().map(|c| c.source.clone()).collect();
+    let source = match kept.as_slice() {
+        [only] => only.clone(),
+        _ => {
+            let listed = format!("median({})", kept.join(","));
+            if listed.len() <= MAX_SOURCE_LEN {
+                listed
+            } else {
+                format!("median({} sources)", kept.len())
+            }
+        }
+    };
+
+    Ok(Aggregate {
+        value,
+        confidence,
+        source,
+        kept,
+        dropped,
+        spread_bps,
+        diverged: components.len() > 1 && spread_bps > settings.divergence_bps as u64,
+    })
+}
+
+/// The middle value, or the mean of the two middle values rounded toward
+/// negative infinity.
+fn median(values: &[i64]) -> i64 {
+    let mut sorted = values.to_vec();
+    sorted.sort_unstable();
+    let mid = sorted.len() / 2;
+
+    if sorted.len() % 2 == 1 {
+        sorted[mid]
+    } else {
+        ((sorted[mid - 1] as i128 + sorted[mid] as i128).div_euclid(2)) as i64
+    }
+}
+
+/// `delta` in basis points of `reference`; unbounded when the reference is 0.
+fn bps(delta: i128, reference: i64) -> u64 {
+    (delta.unsigned_abs() * 10_000)
+        .checked_div(reference.unsigned_abs() as u128)
+        .map_or(u64::MAX, |bps| bps.min(u64::MAX as u128) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn component(source: &str, value: i64) -> Component {
+        Component { source: source.to_string(), value, confidence: 10 }
+    }
+
+    fn settings(min_sources: u32) -> AggregationSettings {
+        AggregationSettings { min_sources, max_outlier_bps: 200, divergence_bps: 100 }
+    }
+
+    #[test]
+    fn test_single_source_passes_through() {
+        let aggregate = aggregate(&[component("pyth", 1_000)], &settings(1)).unwrap();
+
+        assert_eq!((aggregate.value, aggregate.confidence, aggregate.source.as_str()), (1_000, 10, "pyth"));
+        assert!(!aggregate.diverged);
+    }
+
+    #[test]
+    fn test_outlier_is_dropped_and_round_flagged() {
+        let components = [
+            component("binance", 67_345),
+            component("coinbase", 67_341),
+            component("kraken", 67_338),
+            component("stale", 60_000),
+        ];
+
+        let aggregate = aggregate(&components, &settings(3)).unwrap();
+
+        assert_eq!(aggregate.value, 67_341);
+        assert_eq!(aggregate.source, "median(binance,coinbase,kraken)");
+        assert_eq!(aggregate.dropped, vec!["stale".to_string()]);
+        assert_eq!(aggregate.confidence, 10);
+        assert!(aggregate.diverged);
+        assert_eq!(aggregate.spread_bps, 1_090);
+    }
+
+    #[test]
+    fn test_too_few_agreeing_sources() {
+        let components = [component("a", 100), component("b", 150), component("c", 200)];
+
+        let error = aggregate(&components, &settings(2)).unwrap_err();
+        assert!(error.starts_with("1 of 3 sources agree"), "{}", error);
+    }
+
+    #[test]
+    fn test_even_count_median_and_confidence_from_range() {
+        let components = [component("a", 1_000), component("b", 1_010)];
+
+        let aggregate = aggregate(&components, &settings(2)).unwrap();
+        assert_eq!((aggregate.value, aggregate.confidence), (1_005, 10));
+
+        let wide = [component("a", 1_000), component("b", 1_030)];
+        assert_eq!(super::aggregate(&wide, &settings(2)).unwrap().confidence, 15);
+        assert_eq!(median(&[-3, -2]), -3);
+    }
+
+    #[test]
+    fn test_long_source_lists_are_summarised() {
+        let components: Vec<Component> = (0..12).map(|i| component(&format!("exchange-{}", i), 100)).collect();
+
+        assert_eq!(aggregate(&components, &settings(1)).unwrap().source, "median(12 sources)");
+    }
+}
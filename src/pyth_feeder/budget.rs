@@ -0,0 +1,162 @@
+use std::cell::Cell;
+
+use crate::types::{FeederMetrics, FetchSettings};
+
+const NANOS_PER_DAY: u64 = 86_400_000_000_000;
+
+/// Adds up the cycles kept by the calls of one round or one request.
+///
+/// Rounds and requests that overlap across `await`s each carry their own
+/// meter, so no call is charged to another's spending.
+#[derive(Debug, Default)]
+pub struct Meter(Cell<u128>);
+
+impl Meter {
+    /// Records what the call that just returned kept of the `attached`
+    /// cycles.
+    ///
+    /// The refund is only readable until the next `await`, so this must run
+    /// straight after the call, whether it succeeded or not.
+    pub fn record_call(&self, attached: u128) {
+        let refunded = ic_cdk::api::call::msg_cycles_refunded128();
+        self.add(attached.saturating_sub(refunded));
+    }
+
+    fn add(&self, kept: u128) {
+        self.0.set(self.0.get().saturating_add(kept));
+    }
+
+    pub fn spent(&self) -> u128 {
+        self.0.get()
+    }
+}
+
+pub fn day(now: u64) -> u64 {
+    now / NANOS_PER_DAY
+}
+
+/// Cycles spent so far on the day containing `now`.
+pub fn spent_today(metrics: &FeederMetrics, now: u64) -> u128 {
+    if metrics.cycles_day == Some(day(now)) {
+        metrics.cycles_spent_today.unwrap_or(0)
+    } else {
+        0
+    }
+}
+
+/// Adds a round's spending to the metrics, starting a new day's count when
+/// the day has changed.
+pub fn add_spent(metrics: &mut FeederMetrics, spent: u128, now: u64) {
+    let today = spent_today(metrics, now) + spent;
+    metrics.cycles_day = Some(day(now));
+    metrics.cycles_spent_today = Some(today);
+    metrics.cycles_spent = Some(metrics.cycles_spent.unwrap_or(0) + spent);
+    metrics.last_round_cycles = Some(spent);
+}
+
+/// Adds spending outside rounds, such as product discovery, which the round
+/// budgets do not cover.
+pub fn add_spent_outside_rounds(metrics: &mut FeederMetrics, spent: u128) {
+    metrics.cycles_spent_outside_rounds = Some(metrics.cycles_spent_outside_rounds.unwrap_or(0) + spent);
+}
+
+/// What a round may attach: the smaller of the round budget and what is left
+/// of the day's. `None` means unlimited.
+pub fn allowance(settings: &FetchSettings, spent_today: u128) -> Option<u128> {
+    let day_left = settings.cycles_per_day.map(|budget| budget.saturating_sub(spent_today));
+
+    match (settings.cycles_per_round, day_left) {
+        (Some(round), Some(day)) => Some(round.min(day)),
+        (round, day) => round.or(day),
+    }
+}
+
+/// How many of `costs`, taken in order, fit in `allowance`. Batches come in
+/// priority order, so stopping at the first that does not fit never skips a
+/// feed in favour of a lower-priority one.
+pub fn admitted(costs: &[u128], allowance: Option<u128>) -> usize {
+    let Some(mut left) = allowance else {
+        return costs.len();
+    };
+
+    costs
+        .iter()
+        .take_while(|&&cost| match left.checked_sub(cost) {
+            Some(rest) => {
+                left = rest;
+                true
+            }
+            None => false,
+        })
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metrics() -> FeederMetrics {
+        FeederMetrics {
+            total_updates: 0,
+            successful_updates: 0,
+            failed_updates: 0,
+            last_update_time: 0,
+            active_feeds: 0,
+            timer_running: false,
+            cycles_spent: None,
+            cycles_spent_today: None,
+            cycles_day: None,
+            last_round_cycles: None,
+            skipped_for_budget: None,
+            cycles_spent_outside_rounds: None,
+        }
+    }
+
+    #[test]
+    fn test_admits_in_order_until_the_allowance_runs_out() {
+        assert_eq!(admitted(&[3, 3, 3], None), 3);
+        assert_eq!(admitted(&[3, 3, 3], Some(7)), 2);
+        // A cheaper batch later on waits with the rest.
+        assert_eq!(admitted(&[3, 5, 1], Some(7)), 1);
+        assert_eq!(admitted(&[3], Some(0)), 0);
+    }
+
+    #[test]
+    fn test_allowance_is_the_tighter_budget() {
+        let settings = |round, day| FetchSettings { max_concurrent_batches: 4, cycles_per_round: round, cycles_per_day: day };
+
+        assert_eq!(allowance(&settings(None, None), 50), None);
+        assert_eq!(allowance(&settings(Some(10), None), 50), Some(10));
+        assert_eq!(allowance(&settings(None, Some(60)), 50), Some(10));
+        assert_eq!(allowance(&settings(Some(5), Some(60)), 50), Some(5));
+        assert_eq!(allowance(&settings(Some(5), Some(40)), 50), Some(0));
+    }
+
+    #[test]
+    fn test_daily_spend_resets_on_a_new_day() {
+        let mut metrics = metrics();
+
+        add_spent(&mut metrics, 100, NANOS_PER_DAY + 1);
+        add_spent(&mut metrics, 50, NANOS_PER_DAY + 2);
+        assert_eq!(spent_today(&metrics, NANOS_PER_DAY + 3), 150);
+        assert_eq!(spent_today(&metrics, 2 * NANOS_PER_DAY), 0);
+
+        add_spent(&mut metrics, 20, 2 * NANOS_PER_DAY);
+        assert_eq!((metrics.cycles_spent, metrics.cycles_spent_today), (Some(170), Some(20)));
+        assert_eq!((metrics.cycles_day, metrics.last_round_cycles), (Some(2), Some(20)));
+    }
+
+    #[test]
+    fn test_meters_count_separately() {
+        let (round, discovery) = (Meter::default(), Meter::default());
+        round.add(30);
+        discovery.add(5);
+        round.add(10);
+        assert_eq!((round.spent(), discovery.spent()), (40, 5));
+
+        let mut metrics = metrics();
+        add_spent(&mut metrics, round.spent(), NANOS_PER_DAY);
+        add_spent_outside_rounds(&mut metrics, discovery.spent());
+        assert_eq!((metrics.cycles_spent_today, metrics.cycles_spent_outside_rounds), (Some(40), Some(5)));
+    }
+}
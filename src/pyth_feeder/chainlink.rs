@@ -0,0 +1,263 @@
+use futures::future::join_all;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+use crate::budget::Meter;
+use crate::evm_rpc::{self, EvmChain};
+use crate::pyth::PriceStatus;
+use crate::source::{BatchResult, BoxFuture, PriceSource, Quote};
+use crate::types::{EvmRpcSettings, FeedSource, PythFeed};
+
+/// `latestRoundData()`
+pub const LATEST_ROUND_DATA: &str = "0xfeaf968c";
+/// `decimals()`
+pub const DECIMALS: &str = "0x313ce567";
+
+/// Most aggregators update at least hourly; feeds with a longer heartbeat
+/// set `max_age_secs` themselves.
+pub const DEFAULT_MAX_AGE_SECS: u64 = 3_600;
+
+/// Feeds in a batch are read concurrently, so batches are kept small.
+const MAX_FEEDS_PER_BATCH: usize = 10;
+
+thread_local! {
+    // Aggregator decimals never change, so they are read once per aggregator
+    // and kept until the next upgrade.
+    static DECIMALS_CACHE: RefCell<BTreeMap<(String, String), u8>> = const { RefCell::new(BTreeMap::new()) };
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoundData {
+    pub round_id: u128,
+    pub answer: i128,
+    pub started_at: u64,
+    pub updated_at: u64,
+    pub answered_in_round: u128,
+}
+
+/// `0x` followed by 20 bytes of hex.
+pub fn is_valid_address(address: &str) -> bool {
+    address.len() == 42
+        && address.starts_with("0x")
+        && address[2..].bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Decodes the return data of `latestRoundData()`: five 32-byte words.
+pub fn decode_round_data(data: &[u8]) -> Result<RoundData, String> {
+    if data.len() < 5 * 32 {
+        return Err(format!("latestRoundData returned {} bytes, expected 160", data.len()));
+    }
+
+    let word = |index: usize| -> &[u8] { &data[index * 32..(index + 1) * 32] };
+
+    Ok(RoundData {
+        round_id: decode_uint(word(0), "roundId")?,
+        answer: decode_int(word(1))?,
+        started_at: to_u64(decode_uint(word(2), "startedAt")?, "startedAt")?,
+        updated_at: to_u64(decode_uint(word(3), "updatedAt")?, "updatedAt")?,
+        answered_in_round: decode_uint(word(4), "answeredInRound")?,
+    })
+}
+
+pub fn decode_decimals(data: &[u8]) -> Result<u8, String> {
+    let word = data
+        .get(..32)
+        .ok_or_else(|| format!("decimals returned {} bytes, expected 32", data.len()))?;
+    u8::try_from(decode_uint(word, "decimals")?).map_err(|_| "decimals does not fit in uint8".to_string())
+}
+
+/// A `uint` word that must fit in 128 bits.
+fn decode_uint(word: &[u8], name: &str) -> Result<u128, String> {
+    if word[..16].iter().any(|&b| b != 0) {
+        return Err(format!("{} does not fit in 128 bits", name));
+    }
+    Ok(u128::from_be_bytes(word[16..].try_into().expect("16 bytes")))
+}
+
+/// An `int256` word that must fit in 128 bits, i.e. whose upper half is the
+/// sign extension of the lower half.
+fn decode_int(word: &[u8]) -> Result<i128, String> {
+    let value = i128::from_be_bytes(word[16..].try_into().expect("16 bytes"));
+    let extension = if value < 0 { 0xff } else { 0x00 };
+
+    if word[..16].iter().any(|&b| b != extension) {
+        return Err("answer does not fit in 128 bits".to_string());
+    }
+    Ok(value)
+}
+
+fn to_u64(value: u128, name: &str) -> Result<u64, String> {
+    u64::try_from(value).map_err(|_| format!("{} does not fit in 64 bits", name))
+}
+
+/// Turns a round into a quote, rejecting answers a consumer should not use:
+/// non-positive answers, rounds that never completed, and answers older
+/// than `max_age_secs`.
+pub fn to_quote(round: &RoundData, decimals: u8, max_age_secs: u64, now_secs: u64) -> Result<Quote, String> {
+    if round.answer <= 0 {
+        return Err(format!("Chainlink answer is {}", round.answer));
+    }
+    if round.updated_at == 0 {
+        return Err(format!("Chainlink round {} is incomplete", round.round_id));
+    }
+
+    let age_secs = now_secs.saturating_sub(round.updated_at);
+    if age_secs > max_age_secs {
+        return Err(format!("Chainlink answer is {}s old (max {}s)", age_secs, max_age_secs));
+    }
+
+    let value = i64::try_from(round.answer)
+        .map_err(|_| format!("Chainlink answer {} does not fit in 64 bits", round.answer))?;
+
+    Ok(Quote {
+        value,
+        exponent: -(decimals as i32),
+        conf: 0,
+        publish_time: round.updated_at as i64,
+        source: "chainlink".to_string(),
+        status: PriceStatus::Trading,
+        pub_slot: None,
+        curr_slot: None,
+        num_publishers: None,
+        publishers: None,
+        volume: None,
+    })
+}
+
+/// Chainlink aggregators read with `eth_call` through the EVM RPC canister.
+/// Each feed's source names the chain, the aggregator and, optionally, how
+/// old an answer may be.
+pub struct ChainlinkSource {
+    pub settings: EvmRpcSettings,
+}
+
+impl ChainlinkSource {
+    async fn fetch_one(&self, feed: &PythFeed, meter: &Meter) -> Result<Quote, String> {
+        let (chain, aggregator, max_age_secs) = match &feed.source {
+            Some(FeedSource::Chainlink { chain, aggregator, max_age_secs }) => (*chain, aggregator, *max_age_secs),
+            _ => return Err("Feed is not a Chainlink feed".to_string()),
+        };
+
+        let decimals = self.decimals(chain, aggregator, meter).await?;
+        let data = evm_rpc::eth_call(&self.settings, chain, aggregator, LATEST_ROUND_DATA, meter).await?;
+        let round = decode_round_data(&data)?;
+
+        let now_secs = ic_cdk::api::time() / 1_000_000_000;
+        to_quote(&round, decimals, max_age_secs.unwrap_or(DEFAULT_MAX_AGE_SECS), now_secs)
+    }
+
+    async fn decimals(&self, chain: EvmChain, aggregator: &str, meter: &Meter) -> Result<u8, String> {
+        let key = decimals_key(chain, aggregator);
+        if let Some(decimals) = DECIMALS_CACHE.with(|cache| cache.borrow().get(&key).copied()) {
+            return Ok(decimals);
+        }
+
+        let data = evm_rpc::eth_call(&self.settings, chain, aggregator, DECIMALS, meter).await?;
+        let decimals = decode_decimals(&data)?;
+        DECIMALS_CACHE.with(|cache| cache.borrow_mut().insert(key, decimals));
+        Ok(decimals)
+    }
+}
+
+fn decimals_key(chain: EvmChain, aggregator: &str) -> (String, String) {
+    (format!("{:?}", chain), aggregator.to_ascii_lowercase())
+}
+
+impl PriceSource for ChainlinkSource {
+    fn name(&self) -> &'static str {
+        "chainlink"
+    }
+
+    fn max_batch(&self) -> usize {
+        MAX_FEEDS_PER_BATCH
+    }
+
+    /// `latestRoundData()` for every feed, plus `decimals()` for aggregators
+    /// not read before.
+    fn max_cycles(&self, feeds: &[PythFeed]) -> u128 {
+        feeds
+            .iter()
+            .map(|feed| match &feed.source {
+                Some(FeedSource::Chainlink { chain, aggregator, .. }) => {
+                    let cached = DECIMALS_CACHE.with(|cache| cache.borrow().contains_key(&decimals_key(*chain, aggregator)));
+                    if cached { 1 } else { 2 }
+                }
+                _ => 0,
+            })
+            .sum::<u128>()
+            * evm_rpc::CYCLES_PER_CALL
+    }
+
+    fn fetch<'a>(&'a self, feeds: &'a [PythFeed], meter: &'a Meter) -> BoxFuture<'a, BatchResult> {
+        Box::pin(async move {
+            let quotes = join_all(feeds.iter().map(|feed| self.fetch_one(feed, meter))).await;
+            Ok(quotes.into_iter().map(|quote| quote.map(|quote| vec![quote])).collect())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(value: i128) -> Vec<u8> {
+        let extension = if value < 0 { 0xff } else { 0x00 };
+        let mut word = vec![extension; 16];
+        word.extend(value.to_be_bytes());
+        word
+    }
+
+    fn round_data(answer: i128, updated_at: i128) -> Vec<u8> {
+        [word(18_446_744_073_709_562_301), word(answer), word(updated_at), word(updated_at), word(18_446_744_073_709_562_301)]
+            .concat()
+    }
+
+    #[test]
+    fn test_decode_latest_round_data() {
+        let round = decode_round_data(&round_data(262_345_678_901, 1_728_000_000)).unwrap();
+
+        assert_eq!(round.round_id, 18_446_744_073_709_562_301);
+        assert_eq!(round.answer, 262_345_678_901);
+        assert_eq!(round.updated_at, 1_728_000_000);
+
+        assert_eq!(decode_round_data(&round_data(-5, 1)).unwrap().answer, -5);
+        assert!(decode_round_data(&round_data(1, 1)[..159]).is_err());
+
+        let mut huge = round_data(1, 1);
+        huge[32] = 0x01;
+        assert!(decode_round_data(&huge).is_err());
+    }
+
+    #[test]
+    fn test_decode_decimals() {
+        assert_eq!(decode_decimals(&word(8)), Ok(8));
+        assert!(decode_decimals(&word(256)).is_err());
+    }
+
+    #[test]
+    fn test_quote_checks_answer_and_staleness() {
+        let round = decode_round_data(&round_data(262_345_678_901, 1_728_000_000)).unwrap();
+
+        let quote = to_quote(&round, 8, 3_600, 1_728_000_100).unwrap();
+        assert_eq!((quote.value, quote.exponent, quote.publish_time), (262_345_678_901, -8, 1_728_000_000));
+        assert_eq!(quote.source, "chainlink");
+
+        assert_eq!(
+            to_quote(&round, 8, 3_600, 1_728_003_601),
+            Err("Chainlink answer is 3601s old (max 3600s)".to_string())
+        );
+
+        let negative = RoundData { answer: -1, ..round.clone() };
+        assert!(to_quote(&negative, 8, 3_600, 1_728_000_000).is_err());
+
+        let incomplete = RoundData { updated_at: 0, ..round };
+        assert!(to_quote(&incomplete, 8, u64::MAX, 1_728_000_000).is_err());
+    }
+
+    #[test]
+    fn test_address_validation() {
+        assert!(is_valid_address("0x5f4eC3Df9cbd43714FE2740f5E3616155c5b8419"));
+        assert!(!is_valid_address("5f4eC3Df9cbd43714FE2740f5E3616155c5b8419"));
+        assert!(!is_valid_address("0x5f4eC3Df9cbd43714FE2740f5E3616155c5b841"));
+    }
+}
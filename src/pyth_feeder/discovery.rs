@@ -0,0 +1,189 @@
+use candid::{CandidType, Principal};
+use serde::{Deserialize, Serialize};
+
+use crate::budget::Meter;
+use crate::pyth::{self, MappingAccount, ProductAccount};
+use crate::sol_rpc;
+use crate::types::SolanaRpcSettings;
+
+/// Mainnet's products fit in a few mapping accounts; a longer chain is
+/// treated as a loop.
+const MAX_MAPPING_ACCOUNTS: usize = 16;
+
+/// Narrows a discovery. Each set field must match, ignoring case; `symbol`
+/// matches any part of the product's symbol.
+#[derive(Debug, Clone, Default, CandidType, Serialize, Deserialize)]
+pub struct ProductFilter {
+    pub symbol: Option<String>,
+    pub asset_type: Option<String>,
+    pub quote_currency: Option<String>,
+}
+
+impl ProductFilter {
+    pub fn matches(&self, product: &PythProduct) -> bool {
+        let contains = |value: &str, part: &str| value.to_lowercase().contains(&part.to_lowercase());
+        let equals = |value: &Option<String>, expected: &str| {
+            value.as_deref().is_some_and(|value| value.eq_ignore_ascii_case(expected))
+        };
+
+        self.symbol.as_deref().is_none_or(|part| contains(&product.symbol, part))
+            && self.asset_type.as_deref().is_none_or(|t| equals(&product.asset_type, t))
+            && self.quote_currency.as_deref().is_none_or(|q| equals(&product.quote_currency, q))
+    }
+}
+
+/// A Pyth product with a price account, as listed by its product account.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Serialize, Deserialize)]
+pub struct PythProduct {
+    /// Pyth's symbol, such as `Crypto.BTC/USD`.
+    pub symbol: String,
+    /// The symbol `add_discovered_feeds` gives the feed.
+    pub feed_symbol: String,
+    pub asset_type: Option<String>,
+    pub quote_currency: Option<String>,
+    pub product_account: String,
+    pub price_account: String,
+    pub attributes: Vec<(String, String)>,
+}
+
+impl PythProduct {
+    /// `None` for products without a symbol or a price account, which
+    /// cannot be fed.
+    pub fn from_account(product_account: &[u8; 32], account: ProductAccount) -> Option<Self> {
+        let symbol = account.attribute("symbol")?.to_string();
+        let price_account = account.price_account?;
+        let asset_type = account.attribute("asset_type").map(str::to_string);
+        let quote_currency = account.attribute("quote_currency").map(str::to_string);
+
+        Some(PythProduct {
+            feed_symbol: feed_symbol(&symbol, account.attribute("base"), quote_currency.as_deref()),
+            symbol,
+            asset_type,
+            quote_currency,
+            product_account: encode_key(product_account),
+            price_account: encode_key(&price_account),
+            attributes: account.attributes,
+        })
+    }
+}
+
+/// How `add_discovered_feeds` went for one requested symbol.
+#[derive(Debug, Clone, CandidType, Serialize, Deserialize)]
+pub struct FeedAddition {
+    pub symbol: String,
+    pub feed_symbol: Option<String>,
+    pub error: Option<String>,
+}
+
+/// USD-quoted products are named after their base asset, like the feeds
+/// added by hand (`BTC`); others keep both legs (`USD/JPY`).
+fn feed_symbol(symbol: &str, base: Option<&str>, quote: Option<&str>) -> String {
+    match (base, quote) {
+        (Some(base), Some("USD")) => base.to_string(),
+        (Some(base), Some(quote)) => format!("{}/{}", base, quote),
+        _ => symbol.to_string(),
+    }
+}
+
+fn encode_key(key: &[u8; 32]) -> String {
+    bs58::encode(key).into_string()
+}
+
+/// Follows the mapping accounts from `settings.mapping_account` and reads
+/// every product they list, returning those that match `filter`, sorted by
+/// symbol. Product accounts that cannot be read or decoded are skipped.
+pub async fn discover(
+    sol_rpc_canister_id: Principal,
+    settings: &SolanaRpcSettings,
+    filter: &ProductFilter,
+    meter: &Meter,
+) -> Result<Vec<PythProduct>, String> {
+    let mut products = Vec::new();
+    let mut next = Some(
+        settings
+            .mapping_account
+            .clone()
+            .unwrap_or_else(|| pyth::MAINNET_MAPPING_ACCOUNT.to_string()),
+    );
+
+    for _ in 0..MAX_MAPPING_ACCOUNTS {
+        let Some(mapping) = next.take() else {
+            products.sort_by(|a: &PythProduct, b| a.symbol.cmp(&b.symbol));
+            return Ok(products);
+        };
+
+        let accounts = sol_rpc::get_multiple_accounts(sol_rpc_canister_id, settings, std::slice::from_ref(&mapping), meter).await?;
+        let page = accounts
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| Err("Account not found".to_string()))
+            .and_then(|bytes| MappingAccount::parse(&bytes))
+            .map_err(|e| format!("Mapping account {}: {}", mapping, e))?;
+        next = page.next.as_ref().map(encode_key);
+
+        for keys in page.products.chunks(sol_rpc::MAX_ACCOUNTS_PER_REQUEST) {
+            let pubkeys: Vec<String> = keys.iter().map(encode_key).collect();
+            let accounts = sol_rpc::get_multiple_accounts(sol_rpc_canister_id, settings, &pubkeys, meter).await?;
+
+            for ((key, pubkey), account) in keys.iter().zip(&pubkeys).zip(accounts) {
+                match account.and_then(|bytes| ProductAccount::parse(&bytes)) {
+                    Ok(account) => products.extend(
+                        PythProduct::from_account(key, account).filter(|product| filter.matches(product)),
+                    ),
+                    Err(e) => ic_cdk::println!("Skipping product account {}: {}", pubkey, e),
+                }
+            }
+        }
+    }
+
+    Err(format!("More than {} mapping accounts; is the chain a loop?", MAX_MAPPING_ACCOUNTS))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn product(symbol: &str, base: &str, asset_type: &str, quote: &str) -> PythProduct {
+        let attributes = [("symbol", symbol), ("asset_type", asset_type), ("quote_currency", quote), ("base", base)];
+        let account = ProductAccount {
+            price_account: Some([7; 32]),
+            attributes: attributes.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+        };
+        PythProduct::from_account(&[1; 32], account).unwrap()
+    }
+
+    #[test]
+    fn test_products_are_named_like_hand_added_feeds() {
+        let btc = product("Crypto.BTC/USD", "BTC", "Crypto", "USD");
+        assert_eq!(btc.feed_symbol, "BTC");
+        assert_eq!(btc.price_account, bs58::encode([7; 32]).into_string());
+
+        assert_eq!(product("FX.USD/JPY", "USD", "FX", "JPY").feed_symbol, "USD/JPY");
+
+        let unpriced = ProductAccount {
+            price_account: None,
+            attributes: vec![("symbol".to_string(), "Crypto.NEW/USD".to_string())],
+        };
+        assert_eq!(PythProduct::from_account(&[1; 32], unpriced), None);
+    }
+
+    #[test]
+    fn test_filter_fields_must_all_match() {
+        let aapl = product("Equity.US.AAPL/USD", "AAPL", "Equity", "USD");
+
+        assert!(ProductFilter::default().matches(&aapl));
+        assert!(ProductFilter { symbol: Some("aapl".to_string()), ..Default::default() }.matches(&aapl));
+        assert!(ProductFilter {
+            asset_type: Some("equity".to_string()),
+            quote_currency: Some("USD".to_string()),
+            ..Default::default()
+        }
+        .matches(&aapl));
+        assert!(!ProductFilter {
+            asset_type: Some("Equity".to_string()),
+            quote_currency: Some("EUR".to_string()),
+            ..Default::default()
+        }
+        .matches(&aapl));
+    }
+}
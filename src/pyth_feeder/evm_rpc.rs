@@ -0,0 +1,218 @@
+use candid::{CandidType, Principal};
+use serde::{Deserialize, Serialize};
+
+use crate::budget::Meter;
+use crate::sol_rpc::ConsensusStrategy;
+use crate::types::EvmRpcSettings;
+
+/// The EVM RPC canister refunds whatever a call does not use.
+pub const CYCLES_PER_CALL: u128 = 10_000_000_000;
+
+/// Chains with Chainlink aggregators the EVM RPC canister has default
+/// providers for.
+#[derive(CandidType, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EvmChain {
+    EthMainnet,
+    ArbitrumOne,
+    BaseMainnet,
+    OptimismMainnet,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub enum RpcServices {
+    EthMainnet(Option<Vec<EthMainnetService>>),
+    ArbitrumOne(Option<Vec<L2MainnetService>>),
+    BaseMainnet(Option<Vec<L2MainnetService>>),
+    OptimismMainnet(Option<Vec<L2MainnetService>>),
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub enum EthMainnetService {
+    Alchemy,
+    Ankr,
+    BlockPi,
+    PublicNode,
+    Cloudflare,
+    Llama,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub enum L2MainnetService {
+    Alchemy,
+    Ankr,
+    BlockPi,
+    PublicNode,
+    Llama,
+}
+
+impl From<EvmChain> for RpcServices {
+    fn from(chain: EvmChain) -> Self {
+        match chain {
+            EvmChain::EthMainnet => RpcServices::EthMainnet(None),
+            EvmChain::ArbitrumOne => RpcServices::ArbitrumOne(None),
+            EvmChain::BaseMainnet => RpcServices::BaseMainnet(None),
+            EvmChain::OptimismMainnet => RpcServices::OptimismMainnet(None),
+        }
+    }
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct RpcConfig {
+    #[serde(rename = "responseSizeEstimate")]
+    pub response_size_estimate: Option<u64>,
+    #[serde(rename = "responseConsensus")]
+    pub response_consensus: Option<ConsensusStrategy>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub enum BlockTag {
+    Latest,
+    Safe,
+    Finalized,
+}
+
+/// Only the fields an `eth_call` to a view function needs; the canister
+/// treats the remaining optional fields as absent.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct TransactionRequest {
+    pub to: Option<String>,
+    pub input: Option<String>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct CallArgs {
+    pub transaction: TransactionRequest,
+    pub block: Option<BlockTag>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub enum MultiCallResult {
+    Consistent(CallResult),
+    Inconsistent(Vec<(candid::Reserved, CallResult)>),
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub enum CallResult {
+    Ok(String),
+    Err(RpcError),
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub enum RpcError {
+    ProviderError(candid::Reserved),
+    HttpOutcallError(candid::Reserved),
+    JsonRpcError(JsonRpcError),
+    ValidationError(candid::Reserved),
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct JsonRpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl std::fmt::Display for RpcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RpcError::ProviderError(_) => write!(f, "provider error"),
+            RpcError::HttpOutcallError(_) => write!(f, "HTTP outcall error"),
+            RpcError::JsonRpcError(e) => write!(f, "JSON-RPC error {}: {}", e.code, e.message),
+            RpcError::ValidationError(_) => write!(f, "validation error"),
+        }
+    }
+}
+
+/// Calls a view function and returns the raw return data.
+///
+/// `input` is the ABI-encoded call as `0x`-prefixed hex. Inconsistent
+/// provider answers are settled the same way as for the SOL RPC canister: a
+/// value must be returned by more than half of the providers.
+pub async fn eth_call(
+    settings: &EvmRpcSettings,
+    chain: EvmChain,
+    to: &str,
+    input: &str,
+    meter: &Meter,
+) -> Result<Vec<u8>, String> {
+    let rpc_config = settings.consensus.clone().map(|consensus| RpcConfig {
+        response_size_estimate: None,
+        response_consensus: Some(consensus),
+    });
+    let args = CallArgs {
+        transaction: TransactionRequest {
+            to: Some(to.to_string()),
+            input: Some(input.to_string()),
+        },
+        block: Some(BlockTag::Latest),
+    };
+
+    let call_result: Result<(MultiCallResult,), _> = ic_cdk::api::call::call_with_payment128(
+        settings.canister_id,
+        "eth_call",
+        (RpcServices::from(chain), rpc_config, args),
+        CYCLES_PER_CALL,
+    )
+    .await;
+    meter.record_call(CYCLES_PER_CALL);
+
+    let data = match call_result {
+        Ok((MultiCallResult::Consistent(CallResult::Ok(data)),)) => data,
+        Ok((MultiCallResult::Consistent(CallResult::Err(e)),)) => {
+            return Err(format!("eth_call returned error: {}", e))
+        }
+        Ok((MultiCallResult::Inconsistent(results),)) => resolve_by_majority(results)?,
+        Err(e) => return Err(format!("EVM RPC call failed: {:?}", e)),
+    };
+
+    decode_hex(&data)
+}
+
+fn resolve_by_majority(results: Vec<(candid::Reserved, CallResult)>) -> Result<String, String> {
+    let providers = results.len();
+    let mut tally: Vec<(String, usize)> = Vec::new();
+
+    for (_, result) in results {
+        if let CallResult::Ok(data) = result {
+            match tally.iter_mut().find(|(value, _)| *value == data) {
+                Some((_, count)) => *count += 1,
+                None => tally.push((data, 1)),
+            }
+        }
+    }
+
+    tally
+        .into_iter()
+        .find(|(_, count)| *count * 2 > providers)
+        .map(|(data, _)| data)
+        .ok_or_else(|| format!("EVM RPC providers disagree ({} responses, no majority)", providers))
+}
+
+fn decode_hex(data: &str) -> Result<Vec<u8>, String> {
+    hex::decode(data.trim_start_matches("0x")).map_err(|e| format!("Invalid eth_call result: {}", e))
+}
+
+/// The EVM RPC canister on the IC mainnet.
+pub fn default_canister_id() -> Principal {
+    Principal::from_text("7hfb6-caaaa-aaaar-qadga-cai").expect("valid principal")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ok(data: &str) -> (candid::Reserved, CallResult) {
+        (candid::Reserved, CallResult::Ok(data.to_string()))
+    }
+
+    #[test]
+    fn test_majority_over_all_providers() {
+        assert_eq!(resolve_by_majority(vec![ok("0x01"), ok("0x01"), ok("0x02")]), Ok("0x01".to_string()));
+
+        let failed = (
+            candid::Reserved,
+            CallResult::Err(RpcError::JsonRpcError(JsonRpcError { code: -32000, message: "boom".to_string() })),
+        );
+        assert!(resolve_by_majority(vec![ok("0x01"), failed.clone(), failed]).is_err());
+    }
+}
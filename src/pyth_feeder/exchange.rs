@@ -0,0 +1,510 @@
+use futures::future::join_all;
+use ic_cdk::api::management_canister::http_request::{
+    http_request, CanisterHttpRequestArgument, HttpHeader, HttpMethod, HttpResponse, TransformArgs,
+    TransformContext,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::budget::Meter;
+use crate::fixed_point::{self, RoundingMode};
+use crate::hermes;
+use crate::pyth::PriceStatus;
+use crate::source::{BatchResult, BoxFuture, FeedQuotes, PriceSource, Quote};
+use crate::types::{ExchangeEndpoint, ExchangeTicker, FeedSource, PythFeed};
+
+/// Name of the canister query the replicas run on each response.
+pub const TRANSFORM_METHOD: &str = "transform_exchange_response";
+
+/// Placeholder in `url_template` replaced by the ticker's pair.
+pub const PAIR_PLACEHOLDER: &str = "{pair}";
+
+/// Placeholders in `url_template` replaced by the start and end of the last
+/// closed candle, in Unix seconds or milliseconds.
+pub const TIME_PLACEHOLDERS: [&str; 4] = ["{start}", "{start_ms}", "{end}", "{end_ms}"];
+
+const DEFAULT_CANDLE_SECS: u64 = 60;
+
+/// How long after a candle closes it is first requested, so that exchanges
+/// have settled its last trades.
+const CLOSE_LAG_SECS: u64 = 5;
+
+/// A single closed candle is well under 2 KB on every major exchange.
+const DEFAULT_MAX_RESPONSE_BYTES: u64 = 16_384;
+
+/// Each ticker is a separate outcall, so batches are kept small.
+const MAX_FEEDS_PER_BATCH: usize = 10;
+
+/// One step of an extractor path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Step {
+    Key(String),
+    Index(usize),
+    /// The first element of an array, or the value under the first key (in
+    /// sorted order) of an object. Kraken, for one, nests tickers under the
+    /// pair's canonical name, which differs from the name requested.
+    First,
+}
+
+/// Paths the transform extracts, passed to it as the transform context.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct ExtractPaths {
+    price: String,
+    volume: Option<String>,
+}
+
+/// What remains of a ticker response after the transform.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct ExtractedTicker {
+    price: String,
+    volume: Option<String>,
+}
+
+/// Parses a JSONPath-like extractor: an optional `$`, then keys separated by
+/// `.`, array indices as `[n]`, quoted keys as `["a.b"]`, and `*` or `[*]`
+/// for the first member. `$.result.*.c[0]` reads Kraken's last trade price.
+fn parse_path(path: &str) -> Result<Vec<Step>, String> {
+    let invalid = |reason: &str| format!("Invalid path {:?}: {}", path, reason);
+    let mut rest = path.trim().strip_prefix('$').unwrap_or(path.trim());
+    let mut steps = Vec::new();
+
+    while !rest.is_empty() {
+        if let Some(inner) = rest.strip_prefix('[') {
+            let end = inner.find(']').ok_or_else(|| invalid("unclosed ["))?;
+            let token = &inner[..end];
+            rest = &inner[end + 1..];
+
+            let quoted = token
+                .strip_prefix('"')
+                .and_then(|t| t.strip_suffix('"'))
+                .or_else(|| token.strip_prefix('\'').and_then(|t| t.strip_suffix('\'')));
+
+            steps.push(match quoted {
+                Some(key) => Step::Key(key.to_string()),
+                None if token == "*" => Step::First,
+                None => Step::Index(token.parse().map_err(|_| invalid("index must be a number"))?),
+            });
+        } else {
+            let key = rest.strip_prefix('.').unwrap_or(rest);
+            if key.len() == rest.len() && !steps.is_empty() {
+                return Err(invalid("expected . or ["));
+            }
+
+            let end = key.find(['.', '[']).unwrap_or(key.len());
+            let token = &key[..end];
+            rest = &key[end..];
+
+            steps.push(match token {
+                "" => return Err(invalid("empty key")),
+                _ if token.contains(']') => return Err(invalid("unopened ]")),
+                "*" => Step::First,
+                _ => Step::Key(token.to_string()),
+            });
+        }
+    }
+
+    if steps.is_empty() {
+        return Err(invalid("selects the whole response"));
+    }
+    Ok(steps)
+}
+
+/// Checks a path without a response to apply it to.
+fn validate_path(path: &str) -> Result<(), String> {
+    parse_path(path).map(|_| ())
+}
+
+/// Follows `path` into `value` and returns the number found there as
+/// decimal text. Exchanges quote prices both as strings and as numbers.
+pub fn extract(value: &Value, path: &str) -> Result<String, String> {
+    let mut current = value;
+
+    for step in parse_path(path)? {
+        let next = match (&step, current) {
+            (Step::Key(key), Value::Object(map)) => map.get(key),
+            (Step::Index(index), Value::Array(items)) => items.get(*index),
+            (Step::First, Value::Array(items)) => items.first(),
+            (Step::First, Value::Object(map)) => map.values().next(),
+            _ => None,
+        };
+        current = next.ok_or_else(|| format!("Nothing at {} ({:?} not found)", path, step))?;
+    }
+
+    match current {
+        Value::String(text) => Ok(text.clone()),
+        Value::Number(number) => Ok(number.to_string()),
+        other => Err(format!("Value at {} is not a number: {}", path, other)),
+    }
+}
+
+/// Reduces a candle response to the extracted price and volume.
+///
+/// Replicas must agree on the transformed response byte for byte, so the
+/// headers are dropped and the body replaced by the two fields the feeder
+/// reads. The fields agree because the request names a closed candle,
+/// which no longer changes; a live ticker's last price and rolling volume
+/// move between the replicas' requests. Responses the paths do not match
+/// become the error message.
+pub fn transform(args: TransformArgs) -> HttpResponse {
+    let response = args.response;
+
+    let body = if response.status == 200u16 {
+        match extract_ticker(&response.body, &args.context) {
+            Ok(ticker) => serde_json::to_vec(&ticker).unwrap_or_default(),
+            Err(e) => e.into_bytes(),
+        }
+    } else {
+        response.body
+    };
+
+    HttpResponse {
+        status: response.status,
+        headers: vec![],
+        body,
+    }
+}
+
+fn extract_ticker(body: &[u8], context: &[u8]) -> Result<ExtractedTicker, String> {
+    let paths: ExtractPaths =
+        serde_json::from_slice(context).map_err(|e| format!("Invalid transform context: {}", e))?;
+    let response: Value = serde_json::from_slice(body).map_err(|e| format!("Invalid ticker response: {}", e))?;
+
+    Ok(ExtractedTicker {
+        price: extract(&response, &paths.price)?,
+        volume: paths.volume.as_deref().map(|path| extract(&response, path)).transpose()?,
+    })
+}
+
+/// Checks endpoints before they are stored: names must be usable as an
+/// oracle source and unique, URLs must be HTTPS and take a pair and a candle
+/// time, and paths must parse.
+pub fn validate_endpoints(endpoints: &[ExchangeEndpoint]) -> Result<(), String> {
+    for (i, endpoint) in endpoints.iter().enumerate() {
+        if endpoint.name.is_empty() || endpoint.name.len() > 100 {
+            return Err("Exchange endpoint names must be 1 to 100 bytes".to_string());
+        }
+        if endpoints[..i].iter().any(|other| other.name == endpoint.name) {
+            return Err(format!("Duplicate exchange endpoint {}", endpoint.name));
+        }
+        if !endpoint.url_template.starts_with("https://") {
+            return Err(format!("{} URL must be https", endpoint.name));
+        }
+        if !endpoint.url_template.contains(PAIR_PLACEHOLDER) {
+            return Err(format!("{} URL must contain {}", endpoint.name, PAIR_PLACEHOLDER));
+        }
+        // Without a fixed candle, replicas are answered with prices that
+        // moved between their requests and never reach consensus.
+        if !names_a_candle(endpoint) {
+            return Err(format!("{} URL must contain one of {}", endpoint.name, TIME_PLACEHOLDERS.join(", ")));
+        }
+        if endpoint.candle_secs == Some(0) {
+            return Err(format!("{} candle_secs must be positive", endpoint.name));
+        }
+        validate_path(&endpoint.price_path)?;
+        if let Some(path) = &endpoint.volume_path {
+            validate_path(path)?;
+        }
+    }
+    Ok(())
+}
+
+fn names_a_candle(endpoint: &ExchangeEndpoint) -> bool {
+    TIME_PLACEHOLDERS.iter().any(|placeholder| endpoint.url_template.contains(placeholder))
+}
+
+/// Start of the last candle of `candle_secs` closed at least
+/// `CLOSE_LAG_SECS` before `now_secs`.
+fn candle_start(now_secs: u64, candle_secs: u64) -> u64 {
+    let closed_by = now_secs.saturating_sub(CLOSE_LAG_SECS);
+    (closed_by - closed_by % candle_secs).saturating_sub(candle_secs)
+}
+
+fn candle_secs(endpoint: &ExchangeEndpoint) -> u64 {
+    endpoint.candle_secs.unwrap_or(DEFAULT_CANDLE_SECS).max(1)
+}
+
+pub fn ticker_url(endpoint: &ExchangeEndpoint, pair: &str, start_secs: u64) -> String {
+    let end_secs = start_secs + candle_secs(endpoint);
+    endpoint
+        .url_template
+        .replace(PAIR_PLACEHOLDER, pair)
+        .replace("{start_ms}", &(start_secs * 1_000).to_string())
+        .replace("{end_ms}", &(end_secs * 1_000).to_string())
+        .replace("{start}", &start_secs.to_string())
+        .replace("{end}", &end_secs.to_string())
+}
+
+fn to_quote(endpoint: &str, ticker: &ExtractedTicker, publish_time: u64) -> Result<Quote, String> {
+    let (value, exponent) = fixed_point::parse_decimal(&ticker.price)?;
+    if value <= 0 {
+        return Err(format!("{} price is {}", endpoint, ticker.price));
+    }
+
+    let volume = match &ticker.volume {
+        Some(volume) => {
+            let (units, exponent) = fixed_point::parse_decimal(volume)?;
+            let whole = fixed_point::rescale(units as i128, exponent, 0, RoundingMode::Floor)
+                .map_err(|e| format!("{} volume: {}", endpoint, e))?;
+            Some(u64::try_from(whole).map_err(|_| format!("{} volume is {}", endpoint, volume))?)
+        }
+        None => None,
+    };
+
+    // The price is the candle's close, so the quote is dated when it closed.
+    Ok(Quote {
+        value,
+        exponent,
+        conf: 0,
+        publish_time: publish_time as i64,
+        source: endpoint.to_string(),
+        status: PriceStatus::Trading,
+        pub_slot: None,
+        curr_slot: None,
+        num_publishers: None,
+        publishers: None,
+        volume,
+    })
+}
+
+/// Closing prices of the last closed candle on exchange REST APIs, over
+/// HTTPS outcalls.
+///
+/// A feed lists tickers on any number of configured endpoints and gets one
+/// quote per ticker that answers, so each exchange is a separate input to the
+/// feed's aggregate. The feed only fails when none of them answer.
+pub struct ExchangeSource {
+    pub endpoints: Vec<ExchangeEndpoint>,
+}
+
+impl ExchangeSource {
+    async fn fetch_feed(&self, feed: &PythFeed, meter: &Meter) -> FeedQuotes {
+        let tickers = match &feed.source {
+            Some(FeedSource::Exchange { tickers }) if !tickers.is_empty() => tickers,
+            _ => return Err("Feed has no exchange tickers".to_string()),
+        };
+
+        let mut quotes = Vec::new();
+        let mut errors = Vec::new();
+
+        for ticker in tickers {
+            match self.fetch_ticker(ticker, meter).await {
+                Ok(quote) => quotes.push(quote),
+                Err(e) => {
+                    ic_cdk::println!("✗ {} | {} {}: {}", feed.symbol, ticker.endpoint, ticker.pair, e);
+                    errors.push(format!("{}: {}", ticker.endpoint, e));
+                }
+            }
+        }
+
+        if quotes.is_empty() {
+            return Err(errors.join("; "));
+        }
+        Ok(quotes)
+    }
+
+    fn ticker_cycles(&self, ticker: &ExchangeTicker) -> u128 {
+        self.endpoints
+            .iter()
+            .find(|endpoint| endpoint.name == ticker.endpoint)
+            .map_or(0, |endpoint| {
+                let max_response_bytes = endpoint.max_response_bytes.unwrap_or(DEFAULT_MAX_RESPONSE_BYTES);
+                // Times have ten digits until 2286.
+                let url = ticker_url(endpoint, &ticker.pair, 1_000_000_000);
+                hermes::outcall_cycles(url.len() as u128, max_response_bytes as u128)
+            })
+    }
+
+    async fn fetch_ticker(&self, ticker: &ExchangeTicker, meter: &Meter) -> Result<Quote, String> {
+        let endpoint = self
+            .endpoints
+            .iter()
+            .find(|endpoint| endpoint.name == ticker.endpoint)
+            .ok_or_else(|| format!("No exchange endpoint named {}", ticker.endpoint))?;
+        // Endpoints stored before candles were required still read live tickers.
+        if !names_a_candle(endpoint) {
+            return Err(format!("{} URL names no candle time", endpoint.name));
+        }
+
+        let max_response_bytes = endpoint.max_response_bytes.unwrap_or(DEFAULT_MAX_RESPONSE_BYTES);
+        let context = ExtractPaths {
+            price: endpoint.price_path.clone(),
+            volume: endpoint.volume_path.clone(),
+        };
+
+        let start = candle_start(ic_cdk::api::time() / 1_000_000_000, candle_secs(endpoint));
+        let request = CanisterHttpRequestArgument {
+            url: ticker_url(endpoint, &ticker.pair, start),
+            max_response_bytes: Some(max_response_bytes),
+            method: HttpMethod::GET,
+            headers: vec![HttpHeader {
+                name: "Accept".to_string(),
+                value: "application/json".to_string(),
+            }],
+            body: None,
+            transform: Some(TransformContext::from_name(
+                TRANSFORM_METHOD.to_string(),
+                serde_json::to_vec(&context).unwrap_or_default(),
+            )),
+        };
+
+        let cycles = hermes::outcall_cycles(request.url.len() as u128, max_response_bytes as u128);
+        let response = http_request(request, cycles).await;
+        meter.record_call(cycles);
+        let (response,) = response.map_err(|(code, message)| format!("request failed: {:?} {}", code, message))?;
+
+        let text = String::from_utf8_lossy(&response.body);
+        if response.status != 200u16 {
+            return Err(format!("HTTP {}: {}", response.status, text));
+        }
+
+        let ticker: ExtractedTicker = serde_json::from_slice(&response.body).map_err(|_| text.to_string())?;
+        to_quote(&endpoint.name, &ticker, start + candle_secs(endpoint))
+    }
+}
+
+impl PriceSource for ExchangeSource {
+    fn name(&self) -> &'static str {
+        "exchange"
+    }
+
+    fn max_batch(&self) -> usize {
+        MAX_FEEDS_PER_BATCH
+    }
+
+    fn max_cycles(&self, feeds: &[PythFeed]) -> u128 {
+        feeds
+            .iter()
+            .filter_map(|feed| match &feed.source {
+                Some(FeedSource::Exchange { tickers }) => Some(tickers),
+                _ => None,
+            })
+            .flatten()
+            .map(|ticker| self.ticker_cycles(ticker))
+            .sum()
+    }
+
+    fn fetch<'a>(&'a self, feeds: &'a [PythFeed], meter: &'a Meter) -> BoxFuture<'a, BatchResult> {
+        Box::pin(async move { Ok(join_all(feeds.iter().map(|feed| self.fetch_feed(feed, meter))).await) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BINANCE: &str = r#"{"symbol":"BTCUSDT","lastPrice":"67345.12000000","volume":"18234.50100000","closeTime":1728000000123}"#;
+    const COINBASE: &str = r#"{"data":{"amount":"67341.5","base":"BTC","currency":"USD"}}"#;
+    const BINANCE_KLINE: &str = r#"[[1728000000000,"67310.01","67352.00","67301.10","67345.12","18.234501",1728000059999,"1227904.6",512,"9.1","612771.2","0"]]"#;
+    const KRAKEN: &str = r#"{"error":[],"result":{"XXBTZUSD":{"c":["67338.90000","0.00120000"],"v":["812.1","2101.73929"]}}}"#;
+
+    fn paths(price: &str, volume: Option<&str>) -> Vec<u8> {
+        serde_json::to_vec(&ExtractPaths {
+            price: price.to_string(),
+            volume: volume.map(str::to_string),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_extract_from_exchange_shapes() {
+        let value = |body: &str| serde_json::from_str::<Value>(body).unwrap();
+
+        assert_eq!(extract(&value(BINANCE), "$.lastPrice"), Ok("67345.12000000".to_string()));
+        assert_eq!(extract(&value(COINBASE), "data.amount"), Ok("67341.5".to_string()));
+        assert_eq!(extract(&value(KRAKEN), "$.result.*.c[0]"), Ok("67338.90000".to_string()));
+        assert_eq!(extract(&value(KRAKEN), "$['result'][*].v[1]"), Ok("2101.73929".to_string()));
+        assert_eq!(extract(&value("[1, 2.5]"), "$[1]"), Ok("2.5".to_string()));
+
+        assert!(extract(&value(BINANCE), "$.closeTime.x").is_err());
+        assert!(extract(&value(COINBASE), "$.data").unwrap_err().contains("not a number"));
+    }
+
+    #[test]
+    fn test_invalid_paths() {
+        for path in ["$", "", "a..b", "a[", "a[x]", "a]b"] {
+            assert!(validate_path(path).is_err(), "{:?}", path);
+        }
+        assert!(validate_path(r#"$["a.b"].c"#).is_ok());
+    }
+
+    #[test]
+    fn test_endpoint_validation() {
+        let endpoint = |name: &str, url: &str| ExchangeEndpoint {
+            name: name.to_string(),
+            url_template: url.to_string(),
+            price_path: "$[0][4]".to_string(),
+            volume_path: Some("$[0][5]".to_string()),
+            max_response_bytes: None,
+            candle_secs: None,
+        };
+        let binance = endpoint(
+            "binance",
+            "https://api.binance.com/api/v3/klines?symbol={pair}&interval=1m&startTime={start_ms}&limit=1",
+        );
+
+        assert_eq!(validate_endpoints(std::slice::from_ref(&binance)), Ok(()));
+        assert_eq!(
+            ticker_url(&binance, "BTCUSDT", 1_728_000_000),
+            "https://api.binance.com/api/v3/klines?symbol=BTCUSDT&interval=1m&startTime=1728000000000&limit=1"
+        );
+        assert_eq!(
+            ticker_url(&endpoint("x", "https://x/{pair}?from={start}&to={end}"), "BTC", 1_728_000_000),
+            "https://x/BTC?from=1728000000&to=1728000060"
+        );
+
+        assert!(validate_endpoints(&[binance.clone(), binance.clone()]).is_err());
+        assert!(validate_endpoints(&[endpoint("", "https://x/{pair}/{start}")]).is_err());
+        assert!(validate_endpoints(&[endpoint("x", "https://x/BTCUSDT/{start}")]).is_err());
+        assert!(validate_endpoints(&[endpoint("x", "ftp://x/{pair}/{start}")]).is_err());
+        assert!(validate_endpoints(&[endpoint("x", "http://x/{pair}/{start}")]).is_err());
+        // A live ticker answers each replica differently.
+        assert!(validate_endpoints(&[endpoint("x", "https://x/ticker?symbol={pair}")]).is_err());
+        assert!(validate_endpoints(&[ExchangeEndpoint { candle_secs: Some(0), ..binance.clone() }]).is_err());
+        assert!(validate_endpoints(&[ExchangeEndpoint { volume_path: Some("a[".to_string()), ..binance }]).is_err());
+    }
+
+    #[test]
+    fn test_requests_the_last_settled_candle() {
+        // 12:00:03 is too soon after 12:00 for the 11:59 candle.
+        assert_eq!(candle_start(1_728_000_003, 60), 1_728_000_000 - 120);
+        assert_eq!(candle_start(1_728_000_005, 60), 1_728_000_000 - 60);
+        assert_eq!(candle_start(1_728_000_064, 60), 1_728_000_000 - 60);
+        assert_eq!(candle_start(1_728_000_065, 60), 1_728_000_000);
+        assert_eq!(candle_start(3, 60), 0);
+    }
+
+    #[test]
+    fn test_transform_keeps_only_extracted_fields() {
+        let response = |date: &str, trades: &str| HttpResponse {
+            status: 200u16.into(),
+            headers: vec![HttpHeader { name: "date".to_string(), value: date.to_string() }],
+            body: BINANCE_KLINE.replace(",512,", trades).into_bytes(),
+        };
+        let transform_at = |date: &str, trades: &str| {
+            transform(TransformArgs {
+                response: response(date, trades),
+                context: paths("$[0][4]", Some("$[0][5]")),
+            })
+        };
+
+        let first = transform_at("Fri, 04 Oct 2024 00:01:05 GMT", ",512,");
+        let second = transform_at("Fri, 04 Oct 2024 00:01:06 GMT", ", 512 ,");
+        assert_eq!(first, second);
+        assert!(first.headers.is_empty());
+
+        let ticker: ExtractedTicker = serde_json::from_slice(&first.body).unwrap();
+        let quote = to_quote("binance", &ticker, 1_728_000_060).unwrap();
+        assert_eq!((quote.value, quote.exponent, quote.volume), (6_734_512, -2, Some(18)));
+        assert_eq!((quote.source.as_str(), quote.publish_time), ("binance", 1_728_000_060));
+    }
+
+    #[test]
+    fn test_unmatched_response_becomes_the_error() {
+        let response = transform(TransformArgs {
+            response: HttpResponse { status: 200u16.into(), headers: vec![], body: COINBASE.as_bytes().to_vec() },
+            context: paths("$.lastPrice", None),
+        });
+
+        assert!(String::from_utf8(response.body).unwrap().starts_with("Nothing at $.lastPrice"));
+    }
+}
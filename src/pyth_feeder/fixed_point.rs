@@ -0,0 +1,272 @@
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Oracle prices are integers scaled by 10^6 unless a feed says otherwise.
+pub const DEFAULT_TARGET_DECIMALS: u32 = 6;
+
+/// Largest power of ten that fits in an `i128`.
+pub const MAX_POW10: u32 = 38;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, CandidType, Serialize, Deserialize)]
+pub enum RoundingMode {
+    TowardZero,
+    Floor,
+    Ceiling,
+    HalfUp,
+    #[default]
+    HalfEven,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConversionError {
+    Overflow {
+        value: i128,
+        exponent: i32,
+        target_decimals: u32,
+    },
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConversionError::Overflow { value, exponent, target_decimals } => write!(
+                f,
+                "{}e{} does not fit in {} decimals",
+                value, exponent, target_decimals
+            ),
+        }
+    }
+}
+
+/// Converts `value * 10^exponent` to an integer with `target_decimals`
+/// decimal places, using only integer arithmetic.
+pub fn rescale(
+    value: i128,
+    exponent: i32,
+    target_decimals: u32,
+    mode: RoundingMode,
+) -> Result<i128, ConversionError> {
+    let overflow = || ConversionError::Overflow { value, exponent, target_decimals };
+    let shift = exponent as i64 + target_decimals as i64;
+
+    if shift >= 0 {
+        if value == 0 {
+            return Ok(0);
+        }
+        if shift > MAX_POW10 as i64 {
+            return Err(overflow());
+        }
+        return value.checked_mul(10i128.pow(shift as u32)).ok_or_else(overflow);
+    }
+
+    // Once the divisor exceeds 2 * |value| every rounding mode gives the same
+    // answer, so clamping keeps the divisor representable without changing it.
+    let divisor = 10i128.pow((-shift).min(MAX_POW10 as i64) as u32);
+    Ok(divide(value, divisor, mode))
+}
+
+pub fn rescale_i64(
+    value: i64,
+    exponent: i32,
+    target_decimals: u32,
+    mode: RoundingMode,
+) -> Result<i64, ConversionError> {
+    let scaled = rescale(value as i128, exponent, target_decimals, mode)?;
+    i64::try_from(scaled).map_err(|_| ConversionError::Overflow {
+        value: value as i128,
+        exponent,
+        target_decimals,
+    })
+}
+
+pub fn rescale_u64(
+    value: u64,
+    exponent: i32,
+    target_decimals: u32,
+    mode: RoundingMode,
+) -> Result<u64, ConversionError> {
+    let scaled = rescale(value as i128, exponent, target_decimals, mode)?;
+    u64::try_from(scaled).map_err(|_| ConversionError::Overflow {
+        value: value as i128,
+        exponent,
+        target_decimals,
+    })
+}
+
+/// `value / divisor` rounded with `mode`; `divisor` must be positive.
+pub fn divide(value: i128, divisor: i128, mode: RoundingMode) -> i128 {
+    let quotient = value / divisor;
+    let remainder = value % divisor;
+
+    if remainder == 0 {
+        return quotient;
+    }
+
+    let away_from_zero = quotient + value.signum();
+    let twice_remainder = remainder.unsigned_abs() * 2;
+    let divisor = divisor.unsigned_abs();
+
+    match mode {
+        RoundingMode::TowardZero => quotient,
+        RoundingMode::Floor if value < 0 => away_from_zero,
+        RoundingMode::Floor => quotient,
+        RoundingMode::Ceiling if value > 0 => away_from_zero,
+        RoundingMode::Ceiling => quotient,
+        RoundingMode::HalfUp if twice_remainder >= divisor => away_from_zero,
+        RoundingMode::HalfUp => quotient,
+        RoundingMode::HalfEven if twice_remainder > divisor => away_from_zero,
+        RoundingMode::HalfEven if twice_remainder == divisor && quotient % 2 != 0 => away_from_zero,
+        RoundingMode::HalfEven => quotient,
+    }
+}
+
+/// Most significant digits a parsed decimal keeps; more would not fit the
+/// `i64` mantissa.
+const MAX_DECIMAL_DIGITS: usize = 18;
+
+/// Parses a decimal string such as `"67345.12000000"` or `"1.5e-7"` into a
+/// mantissa and exponent, so `value = mantissa * 10^exponent`.
+///
+/// Trailing fractional zeros are dropped and digits beyond the 18th
+/// significant one are truncated.
+pub fn parse_decimal(text: &str) -> Result<(i64, i32), String> {
+    let invalid = || format!("Invalid decimal: {:?}", text);
+    let text = text.trim();
+
+    let (number, exponent) = match text.find(['e', 'E']) {
+        Some(at) => (&text[..at], text[at + 1..].parse::<i32>().map_err(|_| invalid())?),
+        None => (text, 0),
+    };
+    let (negative, number) = match number.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, number.strip_prefix('+').unwrap_or(number)),
+    };
+    let (integer, fraction) = number.split_once('.').unwrap_or((number, ""));
+
+    if integer.is_empty() && fraction.is_empty()
+        || !integer.bytes().chain(fraction.bytes()).all(|b| b.is_ascii_digit())
+    {
+        return Err(invalid());
+    }
+
+    let fraction = fraction.trim_end_matches('0');
+    let digits = format!("{}{}", integer, fraction);
+    let digits = digits.trim_start_matches('0');
+    let mut exponent = exponent.checked_sub(fraction.len() as i32).ok_or_else(invalid)?;
+
+    let kept = &digits[..digits.len().min(MAX_DECIMAL_DIGITS)];
+    exponent = exponent.checked_add((digits.len() - kept.len()) as i32).ok_or_else(invalid)?;
+
+    let mantissa: i64 = if kept.is_empty() { 0 } else { kept.parse().map_err(|_| invalid())? };
+    Ok((if negative { -mantissa } else { mantissa }, exponent))
+}
+
+/// Renders a scaled integer for logs and candid results that still use `f64`.
+pub fn to_f64(value: i128, decimals: u32) -> f64 {
+    value as f64 / 10f64.powi(decimals as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_rounding_modes_on_ties() {
+        // 2.5 and -2.5 at zero decimals
+        let cases = [
+            (RoundingMode::TowardZero, 2, -2),
+            (RoundingMode::Floor, 2, -3),
+            (RoundingMode::Ceiling, 3, -2),
+            (RoundingMode::HalfUp, 3, -3),
+            (RoundingMode::HalfEven, 2, -2),
+        ];
+
+        for (mode, positive, negative) in cases {
+            assert_eq!(rescale(25, -1, 0, mode), Ok(positive), "{:?}", mode);
+            assert_eq!(rescale(-25, -1, 0, mode), Ok(negative), "{:?}", mode);
+        }
+    }
+
+    #[test]
+    fn test_scale_up_and_down() {
+        // BTC at 6_734_512_345_678 * 10^-8
+        assert_eq!(rescale_i64(6_734_512_345_678, -8, 6, RoundingMode::HalfEven), Ok(67_345_123_457));
+        assert_eq!(rescale_i64(42, 2, 6, RoundingMode::HalfEven), Ok(4_200_000_000));
+    }
+
+    #[test]
+    fn test_sub_unit_prices_round_instead_of_truncating() {
+        // 0.0000008 with 6 decimals is 0.8 units
+        assert_eq!(rescale_i64(8, -7, 6, RoundingMode::TowardZero), Ok(0));
+        assert_eq!(rescale_i64(8, -7, 6, RoundingMode::HalfEven), Ok(1));
+        assert_eq!(rescale_u64(8, -7, 6, RoundingMode::Ceiling), Ok(1));
+    }
+
+    #[test]
+    fn test_overflow_is_reported() {
+        assert!(rescale_i64(i64::MAX, 0, 6, RoundingMode::HalfEven).is_err());
+        assert!(rescale(1, 40, 0, RoundingMode::HalfEven).is_err());
+        assert!(rescale_u64(1, -2, 0, RoundingMode::Floor).is_ok());
+        assert!(rescale_u64(1, 0, 0, RoundingMode::Floor).is_ok());
+        assert!(rescale_u64(u64::MAX, 0, 1, RoundingMode::Floor).is_err());
+    }
+
+    #[test]
+    fn test_extreme_negative_exponent() {
+        assert_eq!(rescale(i64::MAX as i128, -200, 6, RoundingMode::HalfEven), Ok(0));
+        assert_eq!(rescale(1, -200, 6, RoundingMode::Ceiling), Ok(1));
+        assert_eq!(rescale(-1, -200, 6, RoundingMode::Floor), Ok(-1));
+    }
+
+    #[test]
+    fn test_parse_decimal() {
+        assert_eq!(parse_decimal("67345.12000000"), Ok((6_734_512, -2)));
+        assert_eq!(parse_decimal("0.00001234"), Ok((1_234, -8)));
+        assert_eq!(parse_decimal("-42"), Ok((-42, 0)));
+        assert_eq!(parse_decimal("1.5e-7"), Ok((15, -8)));
+        assert_eq!(parse_decimal("2E3"), Ok((2, 3)));
+        assert_eq!(parse_decimal(".5"), Ok((5, -1)));
+        assert_eq!(parse_decimal("0.000"), Ok((0, 0)));
+
+        // 20 significant digits keep the first 18.
+        assert_eq!(parse_decimal("1234567890.1234567891"), Ok((123_456_789_012_345_678, -8)));
+
+        for invalid in ["", ".", "abc", "1.2.3", "1e", "--1", "1,5"] {
+            assert!(parse_decimal(invalid).is_err(), "{:?}", invalid);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn prop_matches_f64_path(price in -1_000_000_000_000i64..1_000_000_000_000i64, exponent in -12i32..=0) {
+            let exact = rescale_i64(price, exponent, DEFAULT_TARGET_DECIMALS, RoundingMode::HalfEven).unwrap();
+            let approx = (price as f64 * 10f64.powf(exponent as f64) * 1_000_000.0).round() as i64;
+
+            // f64 carries ~15.9 significant digits; allow one unit of slack
+            // for ties and one part in 10^14 beyond that.
+            let tolerance = 1 + exact.abs() / 100_000_000_000_000;
+            prop_assert!((exact - approx).abs() <= tolerance, "exact {} vs f64 {}", exact, approx);
+        }
+
+        #[test]
+        fn prop_rounding_modes_bracket_the_exact_value(value in any::<i64>(), exponent in -30i32..=0, decimals in 0u32..=12) {
+            let floor = rescale(value as i128, exponent, decimals, RoundingMode::Floor).unwrap();
+            let ceiling = rescale(value as i128, exponent, decimals, RoundingMode::Ceiling).unwrap();
+            prop_assert!(ceiling - floor <= 1);
+
+            for mode in [RoundingMode::TowardZero, RoundingMode::HalfUp, RoundingMode::HalfEven] {
+                let rounded = rescale(value as i128, exponent, decimals, mode).unwrap();
+                prop_assert!(floor <= rounded && rounded <= ceiling);
+            }
+        }
+
+        #[test]
+        fn prop_scale_up_is_lossless(value in -1_000_000_000i64..1_000_000_000i64, decimals in 0u32..=9) {
+            let scaled = rescale(value as i128, 0, decimals, RoundingMode::HalfEven).unwrap();
+            let back = rescale(scaled, -(decimals as i32), 0, RoundingMode::TowardZero).unwrap();
+            prop_assert_eq!(back, value as i128);
+        }
+    }
+}
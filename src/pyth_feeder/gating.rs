@@ -0,0 +1,183 @@
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::pyth::PriceStatus;
+use crate::source::Quote;
+
+/// Conditions a price must meet before it is published.
+///
+/// Feeds without rules only require `Trading` status. Limits a source cannot
+/// be checked against (such as slot age for `PriceUpdateV2`) are skipped.
+#[derive(Debug, Clone, CandidType, Serialize, Deserialize)]
+pub struct GatingRules {
+    pub require_trading: bool,
+    pub max_slot_age: Option<u64>,
+    pub max_publish_age_secs: Option<u64>,
+    pub max_conf_bps: Option<u32>,
+    pub min_publishers: Option<u32>,
+    pub min_volume: Option<u64>,
+}
+
+impl Default for GatingRules {
+    fn default() -> Self {
+        Self {
+            require_trading: true,
+            max_slot_age: None,
+            max_publish_age_secs: None,
+            max_conf_bps: None,
+            min_publishers: None,
+            min_volume: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Serialize, Deserialize)]
+pub enum Rejection {
+    NotTrading { status: PriceStatus },
+    StaleSlot { age: u64, max: u64 },
+    StalePublishTime { age_secs: u64, max_secs: u64 },
+    WideConfidence { conf_bps: u64, max_bps: u32 },
+    TooFewPublishers { publishers: u32, min: u32 },
+    LowVolume { volume: u64, min: u64 },
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rejection::NotTrading { status } => write!(f, "Status is {:?}, not Trading", status),
+            Rejection::StaleSlot { age, max } => {
+                write!(f, "Price is {} slots old (max {})", age, max)
+            }
+            Rejection::StalePublishTime { age_secs, max_secs } => {
+                write!(f, "Price was published {}s ago (max {}s)", age_secs, max_secs)
+            }
+            Rejection::WideConfidence { conf_bps, max_bps } => {
+                write!(f, "Confidence is {} bps of price (max {})", conf_bps, max_bps)
+            }
+            Rejection::TooFewPublishers { publishers, min } => {
+                write!(f, "{} publishers (min {})", publishers, min)
+            }
+            Rejection::LowVolume { volume, min } => write!(f, "Volume is {} (min {})", volume, min),
+        }
+    }
+}
+
+pub fn check(rules: &GatingRules, price: &Quote, now_secs: i64) -> Result<(), Rejection> {
+    if rules.require_trading && price.status != PriceStatus::Trading {
+        return Err(Rejection::NotTrading { status: price.status });
+    }
+
+    if let (Some(max), Some(pub_slot), Some(curr_slot)) = (rules.max_slot_age, price.pub_slot, price.curr_slot) {
+        let age = curr_slot.saturating_sub(pub_slot);
+        if age > max {
+            return Err(Rejection::StaleSlot { age, max });
+        }
+    }
+
+    if let Some(max_secs) = rules.max_publish_age_secs {
+        let age_secs = now_secs.saturating_sub(price.publish_time).max(0) as u64;
+        if age_secs > max_secs {
+            return Err(Rejection::StalePublishTime { age_secs, max_secs });
+        }
+    }
+
+    if let Some(max_bps) = rules.max_conf_bps {
+        let conf_bps = (price.conf as u128 * 10_000)
+            .checked_div(price.value.unsigned_abs() as u128)
+            .map_or(u64::MAX, |bps| bps.min(u64::MAX as u128) as u64);
+        if conf_bps > max_bps as u64 {
+            return Err(Rejection::WideConfidence { conf_bps, max_bps });
+        }
+    }
+
+    if let (Some(min), Some(publishers)) = (rules.min_publishers, price.num_publishers) {
+        if publishers < min {
+            return Err(Rejection::TooFewPublishers { publishers, min });
+        }
+    }
+
+    if let (Some(min), Some(volume)) = (rules.min_volume, price.volume) {
+        if volume < min {
+            return Err(Rejection::LowVolume { volume, min });
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn price() -> Quote {
+        Quote {
+            value: 100_000_000,
+            exponent: -8,
+            conf: 50_000,
+            publish_time: 1_700_000_000,
+            source: "pyth".to_string(),
+            status: PriceStatus::Trading,
+            pub_slot: Some(1_000),
+            curr_slot: Some(1_002),
+            num_publishers: Some(12),
+            publishers: None,
+            volume: None,
+        }
+    }
+
+    #[test]
+    fn test_default_rules_only_require_trading() {
+        assert_eq!(check(&GatingRules::default(), &price(), 1_800_000_000), Ok(()));
+
+        let halted = Quote { status: PriceStatus::Halted, ..price() };
+        assert_eq!(
+            check(&GatingRules::default(), &halted, 1_700_000_000),
+            Err(Rejection::NotTrading { status: PriceStatus::Halted })
+        );
+    }
+
+    #[test]
+    fn test_each_limit_rejects() {
+        let rules = GatingRules {
+            require_trading: true,
+            max_slot_age: Some(1),
+            max_publish_age_secs: None,
+            max_conf_bps: None,
+            min_publishers: None,
+            min_volume: None,
+        };
+        assert_eq!(check(&rules, &price(), 0), Err(Rejection::StaleSlot { age: 2, max: 1 }));
+
+        let rules = GatingRules { max_slot_age: None, max_publish_age_secs: Some(30), ..rules };
+        assert_eq!(
+            check(&rules, &price(), 1_700_000_031),
+            Err(Rejection::StalePublishTime { age_secs: 31, max_secs: 30 })
+        );
+
+        let rules = GatingRules { max_publish_age_secs: None, max_conf_bps: Some(4), ..rules };
+        assert_eq!(check(&rules, &price(), 0), Err(Rejection::WideConfidence { conf_bps: 5, max_bps: 4 }));
+
+        let rules = GatingRules { max_conf_bps: None, min_publishers: Some(13), ..rules };
+        assert_eq!(check(&rules, &price(), 0), Err(Rejection::TooFewPublishers { publishers: 12, min: 13 }));
+
+        let traded = Quote { volume: Some(99), ..price() };
+        let rules = GatingRules { min_publishers: None, min_volume: Some(100), ..rules };
+        assert_eq!(check(&rules, &traded, 0), Err(Rejection::LowVolume { volume: 99, min: 100 }));
+    }
+
+    #[test]
+    fn test_limits_missing_from_the_format_are_skipped() {
+        let v2 = Quote { curr_slot: None, num_publishers: None, ..price() };
+        let rules = GatingRules {
+            require_trading: true,
+            max_slot_age: Some(0),
+            max_publish_age_secs: None,
+            max_conf_bps: None,
+            min_publishers: Some(100),
+            min_volume: Some(1),
+        };
+
+        assert_eq!(check(&rules, &v2, 0), Ok(()));
+    }
+}
@@ -0,0 +1,353 @@
+use ic_cdk::api::management_canister::http_request::{
+    http_request, CanisterHttpRequestArgument, HttpHeader, HttpMethod, HttpResponse, TransformArgs,
+    TransformContext,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::budget::Meter;
+use crate::pyth::{AccountFormat, ParsedPrice, PriceStatus};
+use crate::types::HermesSettings;
+use crate::wormhole::{self, GuardianSet};
+
+/// Keeps request URLs well below Hermes' limit on query string length.
+pub const MAX_IDS_PER_REQUEST: usize = 50;
+
+/// Name of the canister query the replicas run on each response.
+pub const TRANSFORM_METHOD: &str = "transform_hermes_response";
+
+/// How far behind the current time prices are requested, so that Hermes
+/// already has the update for that second.
+pub const PUBLISH_LAG_SECS: u64 = 5;
+
+/// Transform context asking replicas to keep the signed update in the body.
+const KEEP_BINARY: &[u8] = &[1];
+
+/// Hermes always includes the signed update, roughly 2 KB per feed in hex.
+const RESPONSE_BYTES_BASE: u64 = 2_048;
+const RESPONSE_BYTES_PER_FEED: u64 = 3_072;
+const MAX_RESPONSE_BYTES: u64 = 2_000_000;
+
+/// Outcall pricing on a 13-node subnet.
+const SUBNET_SIZE: u128 = 13;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct LatestPriceResponse {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    binary: Option<BinaryUpdate>,
+    parsed: Vec<HermesPriceUpdate>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct BinaryUpdate {
+    encoding: String,
+    data: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct HermesPriceUpdate {
+    id: String,
+    price: HermesPrice,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    metadata: Option<HermesMetadata>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct HermesPrice {
+    price: String,
+    conf: String,
+    expo: i32,
+    publish_time: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct HermesMetadata {
+    #[serde(default)]
+    slot: Option<u64>,
+}
+
+/// Lowercase hex without the `0x` prefix, the form Hermes returns ids in.
+pub fn normalize_feed_id(feed_id: &str) -> String {
+    feed_id.trim().trim_start_matches("0x").to_ascii_lowercase()
+}
+
+/// Fetches the price each of `feed_ids` had at `publish_time` (Unix
+/// seconds) in one outcall: the first update published at or after it.
+///
+/// Replicas each make the request on their own, and `/latest` answers
+/// differently depending on when it is asked. All replicas build the same
+/// URL, so pinning the publish time gets them the same updates and lets
+/// the transformed responses agree.
+///
+/// Like `sol_rpc::get_multiple_accounts`, the outer error means the request
+/// failed as a whole; otherwise there is one entry per id, in request order.
+/// With a guardian set configured, prices come from the verified accumulator
+/// update and the `parsed` section of the response is ignored.
+pub async fn get_prices_at(
+    settings: &HermesSettings,
+    feed_ids: &[String],
+    publish_time: u64,
+    meter: &Meter,
+) -> Result<Vec<Result<ParsedPrice, String>>, String> {
+    let guardians = settings
+        .guardian_set
+        .as_ref()
+        .map(GuardianSet::from_config)
+        .transpose()?;
+
+    let max_response_bytes = max_response_bytes(settings, feed_ids.len());
+
+    let request = CanisterHttpRequestArgument {
+        url: price_url(&settings.base_url, feed_ids, publish_time),
+        max_response_bytes: Some(max_response_bytes),
+        method: HttpMethod::GET,
+        headers: vec![HttpHeader {
+            name: "Accept".to_string(),
+            value: "application/json".to_string(),
+        }],
+        body: None,
+        transform: Some(TransformContext::from_name(
+            TRANSFORM_METHOD.to_string(),
+            if guardians.is_some() { KEEP_BINARY.to_vec() } else { vec![] },
+        )),
+    };
+
+    let cycles = outcall_cycles(request.url.len() as u128, max_response_bytes as u128);
+    let response = http_request(request, cycles).await;
+    meter.record_call(cycles);
+    let (response,) = response.map_err(|(code, message)| format!("Hermes request failed: {:?} {}", code, message))?;
+
+    if response.status != 200u16 {
+        return Err(format!(
+            "Hermes returned HTTP {}: {}",
+            response.status,
+            String::from_utf8_lossy(&response.body)
+        ));
+    }
+
+    match guardians {
+        Some(guardians) => parse_verified_response(&response.body, feed_ids, &guardians),
+        None => parse_response(&response.body, feed_ids),
+    }
+}
+
+/// The publish time to request in a round running at `now` (nanoseconds).
+pub fn publish_time(now: u64) -> u64 {
+    (now / 1_000_000_000).saturating_sub(PUBLISH_LAG_SECS)
+}
+
+/// Cycles attached to the outcall `get_prices_at` makes for `feed_ids`.
+pub fn request_cycles(settings: &HermesSettings, feed_ids: &[String]) -> u128 {
+    // Publish times have ten digits until 2286.
+    let url = price_url(&settings.base_url, feed_ids, 1_000_000_000);
+    outcall_cycles(url.len() as u128, max_response_bytes(settings, feed_ids.len()) as u128)
+}
+
+fn max_response_bytes(settings: &HermesSettings, feeds: usize) -> u64 {
+    settings.max_response_bytes.unwrap_or_else(|| {
+        (RESPONSE_BYTES_BASE + RESPONSE_BYTES_PER_FEED * feeds as u64).min(MAX_RESPONSE_BYTES)
+    })
+}
+
+fn price_url(base_url: &str, feed_ids: &[String], publish_time: u64) -> String {
+    let ids: Vec<String> = feed_ids
+        .iter()
+        .map(|id| format!("ids[]={}", normalize_feed_id(id)))
+        .collect();
+
+    format!(
+        "{}/v2/updates/price/{}?{}&parsed=true&encoding=hex&ignore_invalid_price_ids=true",
+        base_url.trim_end_matches('/'),
+        publish_time,
+        ids.join("&")
+    )
+}
+
+pub fn outcall_cycles(request_bytes: u128, max_response_bytes: u128) -> u128 {
+    (3_000_000 + 60_000 * SUBNET_SIZE) * SUBNET_SIZE
+        + 400 * SUBNET_SIZE * request_bytes
+        + 800 * SUBNET_SIZE * max_response_bytes
+}
+
+/// Reduces a response to what the feeder reads from it.
+///
+/// Replicas must agree on the transformed response byte for byte, so the
+/// headers (dates, request ids, CDN tags) are dropped and the body is
+/// re-serialized with only the fields the feeder reads, without the signed
+/// `binary` payload unless the request verifies it. The prices themselves
+/// agree because the request pins their publish time. Responses that do not
+/// parse are passed through without headers and fail later in
+/// `parse_response`.
+pub fn transform(args: TransformArgs) -> HttpResponse {
+    let response = args.response;
+
+    let body = match serde_json::from_slice::<LatestPriceResponse>(&response.body) {
+        Ok(mut parsed) if response.status == 200u16 => {
+            if args.context != KEEP_BINARY {
+                parsed.binary = None;
+            }
+            serde_json::to_vec(&parsed).unwrap_or(response.body)
+        }
+        _ => response.body,
+    };
+
+    HttpResponse {
+        status: response.status,
+        headers: vec![],
+        body,
+    }
+}
+
+fn parse_response(body: &[u8], feed_ids: &[String]) -> Result<Vec<Result<ParsedPrice, String>>, String> {
+    let response: LatestPriceResponse =
+        serde_json::from_slice(body).map_err(|e| format!("Invalid Hermes response: {}", e))?;
+
+    Ok(feed_ids
+        .iter()
+        .map(|feed_id| {
+            let feed_id = normalize_feed_id(feed_id);
+            response
+                .parsed
+                .iter()
+                .find(|update| normalize_feed_id(&update.id) == feed_id)
+                .ok_or_else(|| format!("Hermes has no price for feed {}", feed_id))
+                .and_then(to_parsed_price)
+        })
+        .collect())
+}
+
+fn parse_verified_response(
+    body: &[u8],
+    feed_ids: &[String],
+    guardians: &GuardianSet,
+) -> Result<Vec<Result<ParsedPrice, String>>, String> {
+    let response: LatestPriceResponse =
+        serde_json::from_slice(body).map_err(|e| format!("Invalid Hermes response: {}", e))?;
+    let binary = response
+        .binary
+        .ok_or_else(|| "Hermes response has no accumulator update".to_string())?;
+
+    if binary.encoding != "hex" {
+        return Err(format!("Unexpected update encoding {}", binary.encoding));
+    }
+
+    let mut prices = Vec::new();
+    for data in &binary.data {
+        let bytes = hex::decode(data).map_err(|e| format!("Invalid update data: {}", e))?;
+        let update = wormhole::verify_accumulator_update(&bytes, guardians)?;
+        prices.extend(update.messages.iter().map(|m| hex::encode(m.feed_id)).zip(update.parsed_prices()));
+    }
+
+    Ok(feed_ids
+        .iter()
+        .map(|feed_id| {
+            let feed_id = normalize_feed_id(feed_id);
+            prices
+                .iter()
+                .find(|(id, _)| *id == feed_id)
+                .map(|(_, price)| price.clone())
+                .ok_or_else(|| format!("Verified update has no price for feed {}", feed_id))
+        })
+        .collect())
+}
+
+fn to_parsed_price(update: &HermesPriceUpdate) -> Result<ParsedPrice, String> {
+    let price: i64 = update
+        .price
+        .price
+        .parse()
+        .map_err(|_| format!("Invalid Hermes price: {}", update.price.price))?;
+    let conf: u64 = update
+        .price
+        .conf
+        .parse()
+        .map_err(|_| format!("Invalid Hermes confidence: {}", update.price.conf))?;
+
+    if price == 0 {
+        return Err("Price is zero".to_string());
+    }
+
+    // Hermes only serves prices that made it into an update, which Pyth only
+    // produces while the feed is trading.
+    Ok(ParsedPrice {
+        format: AccountFormat::Hermes,
+        price,
+        conf,
+        exponent: update.price.expo,
+        status: PriceStatus::Trading,
+        publish_time: update.price.publish_time,
+        pub_slot: update.metadata.as_ref().and_then(|m| m.slot).unwrap_or(0),
+        curr_slot: None,
+        num_publishers: None,
+        publishers: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BTC: &str = "e62df6c8b4a85fe1a67db44dc12de5db330f7ac66b72dc658afedf0f4a415b43";
+    const ETH: &str = "ff61491a931112ddf1bd8147cd1b641375f79f5825126d665480874634fd0ace";
+
+    const RESPONSE: &str = include_str!("fixtures/hermes_latest.json");
+
+    #[test]
+    fn test_parse_response_selects_each_feed() {
+        let ids = vec![ETH.to_string(), format!("0x{}", BTC.to_uppercase())];
+        let prices = parse_response(RESPONSE.as_bytes(), &ids).unwrap();
+
+        assert!(prices[0].as_ref().unwrap_err().contains("no price"));
+
+        let btc = prices[1].as_ref().unwrap();
+        assert_eq!(btc.format, AccountFormat::Hermes);
+        assert_eq!((btc.price, btc.conf, btc.exponent), (6_734_512_345_678, 2_150_000_000, -8));
+        assert_eq!((btc.publish_time, btc.pub_slot), (1_728_000_000, 170_123_456));
+    }
+
+    #[test]
+    fn test_transform_strips_headers_and_binary() {
+        let response = |date: &str| HttpResponse {
+            status: 200u16.into(),
+            headers: vec![HttpHeader { name: "date".to_string(), value: date.to_string() }],
+            body: RESPONSE.as_bytes().to_vec(),
+        };
+        let transform_at = |date: &str| transform(TransformArgs { response: response(date), context: vec![] });
+
+        let first = transform_at("Fri, 04 Oct 2024 00:00:00 GMT");
+        let second = transform_at("Fri, 04 Oct 2024 00:00:01 GMT");
+
+        assert_eq!(first, second);
+        assert!(first.headers.is_empty());
+        assert!(!String::from_utf8(first.body.clone()).unwrap().contains("binary"));
+        assert!(parse_response(&first.body, &[BTC.to_string()]).unwrap()[0].is_ok());
+    }
+
+    #[test]
+    fn test_replicas_agree_only_on_the_same_update() {
+        let transformed = |body: String| {
+            let response = HttpResponse { status: 200u16.into(), headers: vec![], body: body.into_bytes() };
+            transform(TransformArgs { response, context: vec![] }).body
+        };
+        let other_node = RESPONSE.replace("1728000001", "1728000002").replace("\n", "");
+        let ticked = RESPONSE.replace("6734512345678", "6734598765432");
+
+        // Fields the feeder does not read may differ between the answers.
+        assert_eq!(transformed(RESPONSE.to_string()), transformed(other_node));
+        // A price that moved between requests cannot be reconciled, which is
+        // why the request pins the publish time rather than asking for the
+        // latest update.
+        assert_ne!(transformed(RESPONSE.to_string()), transformed(ticked));
+    }
+
+    #[test]
+    fn test_price_url_pins_the_publish_time() {
+        assert_eq!(publish_time(1_728_000_010 * 1_000_000_000 + 999), 1_728_000_005);
+        assert_eq!(
+            price_url("http://localhost:8080/", &[format!("0x{}", BTC)], 1_728_000_005),
+            format!(
+                "http://localhost:8080/v2/updates/price/1728000005?ids[]={}&parsed=true&encoding=hex&ignore_invalid_price_ids=true",
+                BTC
+            )
+        );
+    }
+}
@@ -1,181 +1,180 @@
+mod aggregation;
+mod budget;
+mod chainlink;
+mod discovery;
+mod evm_rpc;
+mod exchange;
+mod fixed_point;
+mod gating;
+mod hermes;
 mod pyth;
+mod registry;
+mod scheduler;
+mod sol_rpc;
+mod source;
 mod state;
+mod switchboard;
+mod transform;
 mod types;
+mod wormhole;
 
 use candid::{CandidType, Principal};
+use futures::future::join_all;
+use ic_cdk::api::management_canister::http_request::{HttpResponse, TransformArgs};
 use ic_cdk_macros::{init, post_upgrade, query, update};
-use ic_cdk_timers::TimerId;
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
 
-use crate::pyth::PriceAccount;
+use crate::discovery::{FeedAddition, ProductFilter, PythProduct};
+use crate::fixed_point::{RoundingMode, DEFAULT_TARGET_DECIMALS};
+use crate::gating::{GatingRules, Rejection};
+use crate::pyth::{ParsedPrice, PublisherStats};
+use crate::source::{BatchResult, FeedQuotes, Quote};
 use crate::state::*;
 use crate::types::*;
 
-static mut TIMER_ID: Option<TimerId> = None;
-
-#[derive(CandidType, Serialize, Deserialize)]
+#[derive(Clone, CandidType, Serialize, Deserialize)]
 struct OraclePriceUpdate {
     symbol: String,
-    price: i64,
-    confidence: Option<u64>,
-    timestamp: u64,
-    source: String,
-}
-
-#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
-enum RpcSources {
-    #[serde(rename = "Default")]
-    Default(SolanaCluster),
+    price: OraclePrice,
 }
 
-#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
-enum SolanaCluster {
-    Mainnet,
-    Devnet,
-    Testnet,
+/// An aggregated update along with the rules that decide, per target,
+/// whether it is sent.
+struct PendingUpdate {
+    update: OraclePriceUpdate,
+    push_rules: Option<PushRules>,
 }
 
-#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
-enum GetAccountInfoEncoding {
-    #[serde(rename = "base58")]
-    base58,
-    #[serde(rename = "base64")]
-    base64,
-    #[serde(rename = "base64+zstd")]
-    base64zstd,
-    #[serde(rename = "jsonParsed")]
-    jsonParsed,
-}
-
-#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
-struct GetAccountInfoParams {
-    pub pubkey: String,
-    pub commitment: Option<String>,
-    pub encoding: Option<GetAccountInfoEncoding>,
-    pub dataSlice: Option<()>,
-    pub minContextSlot: Option<u64>,
+#[derive(Clone, CandidType, Serialize, Deserialize)]
+struct OraclePrice {
+    value: u64,
+    confidence: Option<u64>,
+    timestamp: u64,
+    source: String,
 }
 
-#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
-enum MultiGetAccountInfoResult {
-    Consistent(GetAccountInfoResult),
-    Inconsistent(Vec<((), GetAccountInfoResult)>),
+/// A price in the oracle's integer representation.
+#[derive(Clone)]
+struct ScaledPrice {
+    value: i64,
+    confidence: u64,
+    decimals: u32,
+    source: String,
 }
 
-#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
-enum GetAccountInfoResult {
-    Ok(Option<AccountInfo>),
-    Err(()),
+/// A feed's prices for the round, before they are recorded.
+struct FeedOutcome {
+    feed: PythFeed,
+    prices: Result<Vec<ScaledPrice>, FeedError>,
+    latency_ns: u64,
 }
 
-#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
-struct AccountInfo {
-    pub lamports: u64,
-    pub data: AccountData,
-    pub owner: String,
-    pub executable: bool,
-    pub rentEpoch: u64,
-    pub space: u64,
+/// A passing quote waiting to be aggregated with the others for its oracle
+/// symbol.
+struct Candidate {
+    feed_symbol: String,
+    oracle_symbol: String,
+    push_rules: Option<PushRules>,
+    price: ScaledPrice,
 }
 
-#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
-enum AccountData {
-    #[serde(rename = "legacyBinary")]
-    legacyBinary(String),
-    #[serde(rename = "binary")]
-    binary((String, AccountEncoding)),
+enum FeedError {
+    Failed(String),
+    Rejected(Rejection),
 }
 
-#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
-enum AccountEncoding {
-    binary,
-    base58,
-    base64,
-    #[serde(rename = "base64+zstd")]
-    base64zstd,
-    jsonParsed,
+impl From<String> for FeedError {
+    fn from(e: String) -> Self {
+        FeedError::Failed(e)
+    }
 }
 
 #[init]
 fn init(args: InitArgs) {
+    let admin = args.admin.unwrap_or_else(ic_cdk::caller);
+    if admin == Principal::anonymous() {
+        ic_cdk::trap("The admin cannot be the anonymous principal; pass `admin` or install with an identity");
+    }
+    set_role(&admin, Role::Admin);
+
     let config = FeederConfig {
         oracle_canister_id: args.oracle_canister_id.unwrap_or(Principal::anonymous()),
         sol_rpc_canister_id: args.sol_rpc_canister_id,
         update_interval_secs: args.update_interval_secs.unwrap_or(10),
+        solana_rpc: args.solana_rpc,
+        quarantine: None,
+        hermes: None,
+        evm_rpc: None,
+        exchanges: None,
+        aggregation: None,
+        fetch: None,
+        oracle_targets: None,
+        register_symbols: None,
     };
     set_config(config);
-    ic_cdk::println!("🚀 Pyth Feeder initialized (Testing mode - logging only)");
+    ic_cdk::println!("🚀 Pyth Feeder initialized with admin {}", admin);
 }
 
 #[post_upgrade]
 fn post_upgrade() {
-    let manager = crate::state::get_manager();
-    if manager == Principal::anonymous() {
-        let caller = ic_cdk::caller();
-        crate::state::set_manager(caller);
-        ic_cdk::println!("Manager set to: {}", caller);
+    let from = migrate();
+    if from < SCHEMA_VERSION {
+        ic_cdk::println!("Migrated stable data from schema version {} to {}", from, SCHEMA_VERSION);
     }
 
-    let config = get_config();
-    if config.oracle_canister_id != Principal::anonymous() {
-        start_timer_internal();
+    if roles_is_empty() {
+        let legacy_manager = get_legacy_manager();
+        let admin = if legacy_manager != Principal::anonymous() {
+            legacy_manager
+        } else {
+            ic_cdk::caller()
+        };
+        set_role(&admin, Role::Admin);
+        ic_cdk::println!("Admin set to: {}", admin);
     }
+
+    let config = get_config();
+    scheduler::restore(
+        config.update_interval_secs,
+        config.oracle_canister_id != Principal::anonymous(),
+    );
 }
 
 #[update]
 fn start_timer() {
     let caller = ic_cdk::caller();
-    if caller == Principal::anonymous() {
-        ic_cdk::trap("Anonymous principal cannot start timer");
+    if !has_role(&caller, Role::Operator) {
+        ic_cdk::trap("Only operator can start timer");
     }
 
-    start_timer_internal();
-}
-
-fn start_timer_internal() {
-    unsafe {
-        if TIMER_ID.is_some() {
-            return;
-        }
-    }
-
-    let config = get_config();
-    let interval = Duration::from_secs(config.update_interval_secs);
-
-    let timer_id = ic_cdk_timers::set_timer_interval(interval, || {
-        ic_cdk::spawn(async {
-            let _ = fetch_and_push_prices().await;
-        });
-    });
-
-    unsafe {
-        TIMER_ID = Some(timer_id);
-    }
-
-    update_metrics(|m| m.timer_running = true);
+    scheduler::start(get_config().update_interval_secs);
 }
 
 #[update]
 fn stop_timer() {
     let caller = ic_cdk::caller();
-    if caller == Principal::anonymous() {
-        ic_cdk::trap("Anonymous principal cannot stop timer");
-    }
-
-    unsafe {
-        if let Some(timer_id) = TIMER_ID {
-            ic_cdk_timers::clear_timer(timer_id);
-            TIMER_ID = None;
-        }
+    if !has_role(&caller, Role::Operator) {
+        ic_cdk::trap("Only operator can stop timer");
     }
 
-    update_metrics(|m| m.timer_running = false);
+    scheduler::stop();
 }
 
 async fn fetch_and_push_prices() -> Result<Vec<FeedUpdateResult>, String> {
+    let _round = scheduler::RoundGuard::acquire()
+        .ok_or_else(|| "A fetch round is already in progress".to_string())?;
+
     let config = get_config();
-    let feeds = get_enabled_feeds();
+    let quarantine = config.quarantine.clone().unwrap_or_default();
+    let now = ic_cdk::api::time();
+    let feeds: Vec<PythFeed> = get_enabled_feeds()
+        .into_iter()
+        .filter(|feed| {
+            crate::state::get_feed_health(&feed.symbol).is_none_or(|health| {
+                health.is_due(now, config.update_interval_secs, quarantine.max_backoff_secs)
+            })
+        })
+        .collect();
 
     if feeds.is_empty() {
         return Ok(vec![]);
@@ -183,198 +182,534 @@ async fn fetch_and_push_prices() -> Result<Vec<FeedUpdateResult>, String> {
 
     ic_cdk::println!("🔄 Starting price fetch for {} feeds...", feeds.len());
 
+    let started_at = ic_cdk::api::time();
     let mut results = Vec::new();
-    let mut oracle_updates = Vec::new();
+    let mut candidates = Vec::new();
+    let mut outcomes = Vec::new();
+
+    let fetch_settings = config.fetch.clone().unwrap_or_default();
+    let mut batches = source::plan_batches(feeds, &config);
+    let allowance = budget::allowance(&fetch_settings, budget::spent_today(&get_metrics(), started_at));
+    let costs: Vec<u128> = batches.iter().map(|batch| batch.max_cycles).collect();
+    let skipped: Vec<String> = batches
+        .split_off(budget::admitted(&costs, allowance))
+        .into_iter()
+        .flat_map(|batch| batch.feeds)
+        .map(|feed| feed.symbol)
+        .collect();
+
+    if !skipped.is_empty() {
+        ic_cdk::println!("💸 Cycle budget exhausted, skipping {} feeds: {}", skipped.len(), skipped.join(","));
+        update_metrics(|m| m.skipped_for_budget = Some(m.skipped_for_budget.unwrap_or(0) + skipped.len() as u64));
+    }
 
-    for feed in feeds {
-        match fetch_pyth_price(&config, &feed).await {
-            Ok((price, confidence)) => {
-                let timestamp = ic_cdk::api::time();
-
-                ic_cdk::println!(
-                    "✓ {} | Price: ${:.2} | Confidence: ±${:.2} | Timestamp: {}",
-                    feed.symbol,
-                    price,
-                    confidence,
-                    timestamp
-                );
-
-                oracle_updates.push(OraclePriceUpdate {
-                    symbol: feed.symbol.clone(),
-                    price: (price * 1_000_000.0) as i64,
-                    confidence: Some((confidence * 1_000_000.0) as u64),
-                    timestamp,
-                    source: "pyth".to_string(),
-                });
+    let meter = budget::Meter::default();
+    for wave in batches.chunks(fetch_settings.max_concurrent_batches.max(1) as usize) {
+        let meter = &meter;
+        let fetches = wave.iter().map(|batch| async move {
+            let requested_at = ic_cdk::api::time();
+            let quotes = batch.adapter.fetch(&batch.feeds, meter).await;
+            (quotes, ic_cdk::api::time().saturating_sub(requested_at))
+        });
 
-                results.push(FeedUpdateResult {
-                    symbol: feed.symbol.clone(),
-                    success: true,
-                    error: None,
-                    price: Some(price),
-                    confidence: Some(confidence),
-                });
+        for (batch, (quotes, latency_ns)) in wave.iter().zip(join_all(fetches).await) {
+            outcomes.extend(evaluate_batch(&batch.feeds, quotes, latency_ns));
+        }
+    }
 
-                update_metrics(|m| m.successful_updates += 1);
-            }
-            Err(e) => {
-                ic_cdk::println!(
-                    "✗ {} | Error: {}",
-                    feed.symbol,
-                    e
-                );
-
-                results.push(FeedUpdateResult {
-                    symbol: feed.symbol.clone(),
-                    success: false,
-                    error: Some(e),
-                    price: None,
-                    confidence: None,
-                });
+    apply_multipliers(&mut outcomes);
+    for outcome in outcomes {
+        record_feed_outcome(&outcome.feed, outcome.prices, outcome.latency_ns, &mut results, &mut candidates);
+    }
 
-                update_metrics(|m| m.failed_updates += 1);
+    let feed_symbols: Vec<(String, String)> = candidates
+        .iter()
+        .map(|c| (c.feed_symbol.clone(), c.oracle_symbol.clone()))
+        .collect();
+    let settings = config.aggregation.clone().unwrap_or_default();
+    let (oracle_updates, mut aggregates) = aggregate_candidates(candidates, &settings, ic_cdk::api::time());
+
+    let deliveries = deliver(&config.targets(), &oracle_updates).await;
+    let now = ic_cdk::api::time();
+
+    // Each target's push rules are judged against what it was last sent, so
+    // only the targets that took an update record it.
+    for (delivery, symbols) in deliveries.iter().filter(|(delivery, _)| delivery.error.is_none()) {
+        for pending in oracle_updates.iter().filter(|p| symbols.contains(&p.update.symbol)) {
+            let update = &pending.update;
+            let last_push = LastPush { price: update.price.value as i64, timestamp: update.price.timestamp };
+            set_target_last_push(&delivery.target, &update.symbol, last_push.clone());
+            set_last_push(&update.symbol, last_push);
+            if let Some(aggregate) = aggregates.iter_mut().find(|a| a.symbol == update.symbol) {
+                aggregate.pushed = true;
             }
+            for (feed_symbol, _) in feed_symbols.iter().filter(|(_, oracle)| *oracle == update.symbol) {
+                if let Some(result) = results.iter_mut().find(|r| r.symbol == *feed_symbol) {
+                    result.pushed = true;
+                }
+            }
+        }
+    }
+
+    for aggregate in aggregates.iter_mut().filter(|a| a.error.is_none() && a.skipped.is_none()) {
+        if !deliveries.iter().any(|(_, symbols)| symbols.contains(&aggregate.symbol)) {
+            aggregate.skipped = Some("Not due under the push rules for any target".to_string());
         }
     }
 
-    if !oracle_updates.is_empty() {
-        ic_cdk::println!("📊 Fetched {} prices successfully (Oracle push disabled for testing)", oracle_updates.len());
+    let deliveries: Vec<TargetDelivery> = deliveries.into_iter().map(|(delivery, _)| delivery).collect();
+    for delivery in &deliveries {
+        record_delivery(delivery, now);
     }
 
+    let finished_at = ic_cdk::api::time();
+    for result in &results {
+        record_feed_health(result, finished_at);
+    }
+    quarantine_failing_feeds(&results, &quarantine, finished_at);
+
+    append_round(RoundRecord {
+        id: next_round_id(),
+        started_at,
+        finished_at,
+        results: results.clone(),
+        aggregates: Some(aggregates),
+        skipped: Some(skipped),
+        deliveries: Some(deliveries),
+    });
+
+    let spent = meter.spent();
     update_metrics(|m| {
         m.total_updates += 1;
-        m.last_update_time = ic_cdk::api::time();
+        m.last_update_time = finished_at;
+        budget::add_spent(m, spent, finished_at);
     });
 
     Ok(results)
 }
 
-async fn fetch_pyth_price(config: &FeederConfig, feed: &PythFeed) -> Result<(f64, f64), String> {
-    let params = GetAccountInfoParams {
-        pubkey: feed.account.clone(),
-        commitment: None,
-        encoding: Some(GetAccountInfoEncoding::base64),
-        dataSlice: None,
-        minContextSlot: None,
-    };
-
-    let sources = RpcSources::Default(SolanaCluster::Mainnet);
-    let rpc_config: Option<()> = None;
+fn quarantine_failing_feeds(results: &[FeedUpdateResult], settings: &QuarantineSettings, now: u64) {
+    let mut quarantined = false;
 
-    let cycles = 3_000_000_000u128;
-
-    let call_result: Result<(MultiGetAccountInfoResult,), _> = ic_cdk::api::call::call_with_payment128(
-        config.sol_rpc_canister_id,
-        "getAccountInfo",
-        (sources, rpc_config, params),
-        cycles
-    ).await;
-
-    match call_result {
-        Ok((MultiGetAccountInfoResult::Consistent(GetAccountInfoResult::Ok(Some(account_info))),)) => {
-            let bytes = match account_info.data {
-                AccountData::binary((base64_data, _)) => {
-                    use base64::Engine;
-                    base64::engine::general_purpose::STANDARD.decode(&base64_data)
-                        .map_err(|e| format!("Failed to decode base64: {}", e))?
-                }
-                AccountData::legacyBinary(base64_data) => {
-                    use base64::Engine;
-                    base64::engine::general_purpose::STANDARD.decode(&base64_data)
-                        .map_err(|e| format!("Failed to decode base64: {}", e))?
-                }
-            };
+    for result in results.iter().filter(|r| !r.success) {
+        let failures = crate::state::get_feed_health(&result.symbol)
+            .map_or(0, |health| health.consecutive_failures);
+        if failures < settings.max_consecutive_failures {
+            continue;
+        }
 
+        if let Some(mut feed) = get_feed(&result.symbol) {
+            let reason = result.error.clone().unwrap_or_else(|| "unknown error".to_string());
             ic_cdk::println!(
-                "DEBUG {} | Account data length: {} bytes | First 50 bytes: {:?}",
+                "⛔ {} quarantined after {} consecutive failures: {}",
                 feed.symbol,
-                bytes.len(),
-                &bytes[..bytes.len().min(50)]
+                failures,
+                reason
             );
 
-            if bytes.len() == 134 {
-                if bytes.len() < 117 {
-                    return Err("Account too small for new Pyth format".to_string());
+            feed.enabled = false;
+            feed.quarantine = Some(QuarantineInfo {
+                reason,
+                quarantined_at: now,
+                consecutive_failures: failures,
+            });
+            add_feed(feed);
+            quarantined = true;
+        }
+    }
+
+    if quarantined {
+        let active_feeds = get_enabled_feeds().len() as u32;
+        update_metrics(|m| m.active_feeds = active_feeds);
+    }
+}
+
+/// Gates and scales a batch fetched in a single request, giving one outcome
+/// per feed.
+fn evaluate_batch(feeds: &[PythFeed], quotes: BatchResult, latency_ns: u64) -> Vec<FeedOutcome> {
+    match quotes {
+        Ok(quotes) => feeds
+            .iter()
+            .zip(quotes)
+            .map(|(feed, quotes)| {
+                let stats = quotes.as_ref().ok().and_then(|quotes| quotes.first()?.publishers.clone());
+                if let Some(stats) = stats {
+                    record_publishers(&feed.symbol, stats);
                 }
 
-                let price_bytes = &bytes[109..117];
-                let raw_price = i64::from_le_bytes([
-                    price_bytes[0], price_bytes[1], price_bytes[2], price_bytes[3],
-                    price_bytes[4], price_bytes[5], price_bytes[6], price_bytes[7],
-                ]);
+                FeedOutcome {
+                    feed: feed.clone(),
+                    prices: evaluate_quotes(feed, quotes),
+                    latency_ns,
+                }
+            })
+            .collect(),
+        Err(e) => feeds
+            .iter()
+            .map(|feed| FeedOutcome {
+                feed: feed.clone(),
+                prices: Err(FeedError::Failed(e.clone())),
+                latency_ns,
+            })
+            .collect(),
+    }
+}
 
-                let price = (raw_price as f64) * 1e-8;
-                let confidence = 0.0;
+/// Multiplies the prices of feeds whose transform has a `multiply_by` by
+/// that feed's first passing price this round, taken before any
+/// multiplication of its own. A feed whose multiplier has no price this
+/// round fails.
+fn apply_multipliers(outcomes: &mut [FeedOutcome]) {
+    let multipliers: Vec<(String, ScaledPrice)> = outcomes
+        .iter()
+        .filter_map(|outcome| {
+            let first = outcome.prices.as_ref().ok()?.first()?;
+            Some((outcome.feed.symbol.clone(), first.clone()))
+        })
+        .collect();
+
+    for outcome in outcomes.iter_mut() {
+        let transform = outcome.feed.transform();
+        let (Some(other), Ok(prices)) = (&transform.multiply_by, &outcome.prices) else {
+            continue;
+        };
+
+        let Some((_, multiplier)) = multipliers.iter().find(|(symbol, _)| symbol == other) else {
+            outcome.prices = Err(FeedError::Failed(format!("No price for {} this round", other)));
+            continue;
+        };
+
+        let rounding = transform.rounding.unwrap_or_default();
+        outcome.prices = prices
+            .iter()
+            .map(|price| {
+                let (value, confidence) = transform::multiply(
+                    (price.value, price.confidence),
+                    (multiplier.value, multiplier.confidence, multiplier.decimals),
+                    rounding,
+                )?;
+                let source = format!("{}*{}", price.source, multiplier.source);
+
+                Ok(ScaledPrice {
+                    value,
+                    confidence,
+                    decimals: price.decimals,
+                    source: if source.len() <= 100 { source } else { price.source.clone() },
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()
+            .map_err(FeedError::Failed);
+    }
+}
 
-                ic_cdk::println!(
-                    "DEBUG {} | New format | Raw price: {} | Price: ${:.2}",
-                    feed.symbol,
-                    raw_price,
-                    price
-                );
+/// Gates and scales each of a feed's quotes. The feed succeeds with the
+/// quotes that pass, or fails with the first quote's error if none do.
+fn evaluate_quotes(feed: &PythFeed, quotes: FeedQuotes) -> Result<Vec<ScaledPrice>, FeedError> {
+    let mut passed = Vec::new();
+    let mut first_error = None;
 
-                if raw_price == 0 {
-                    return Err("Price is zero".to_string());
+    for quote in quotes? {
+        let source = quote.source.clone();
+        match evaluate_quote(feed, quote) {
+            Ok(scaled) => passed.push(scaled),
+            Err(e) => {
+                if let FeedError::Rejected(rejection) = &e {
+                    ic_cdk::println!("✗ {} | {} rejected: {}", feed.symbol, source, rejection);
                 }
+                first_error.get_or_insert(e);
+            }
+        }
+    }
 
-                Ok((price, confidence))
-            } else {
-                let price_account = PriceAccount::parse(&bytes)?;
+    if passed.is_empty() {
+        return Err(first_error.unwrap_or_else(|| FeedError::Failed("Source returned no quotes".to_string())));
+    }
+    Ok(passed)
+}
 
-                ic_cdk::println!(
-                    "DEBUG {} | Legacy format | Status: {:?} | Raw price: {} | Exponent: {}",
-                    feed.symbol,
-                    price_account.agg.status,
-                    price_account.agg.price,
-                    price_account.exponent
-                );
+fn evaluate_quote(feed: &PythFeed, quote: Quote) -> Result<ScaledPrice, FeedError> {
+    let rules = feed.gating.clone().unwrap_or_default();
+    let now_secs = (ic_cdk::api::time() / 1_000_000_000) as i64;
+    gating::check(&rules, &quote, now_secs).map_err(FeedError::Rejected)?;
 
-                let price = price_account.get_price_as_f64();
-                let confidence = price_account.get_confidence_as_f64();
+    Ok(scale_quote(feed, quote)?)
+}
 
-                if !price_account.is_valid() {
-                    return Err(format!("Price not valid (status: {:?}, price: {})", price_account.agg.status, price));
-                }
+/// Records a feed's outcome and hands every passing quote on for
+/// aggregation. The first passing quote (there is always one) is the one
+/// reported.
+fn record_feed_outcome(
+    feed: &PythFeed,
+    outcome: Result<Vec<ScaledPrice>, FeedError>,
+    latency_ns: u64,
+    results: &mut Vec<FeedUpdateResult>,
+    candidates: &mut Vec<Candidate>,
+) {
+    match outcome {
+        Ok(prices) => {
+            let timestamp = ic_cdk::api::time();
+            let scaled = &prices[0];
+            let price = fixed_point::to_f64(scaled.value as i128, scaled.decimals);
+            let confidence = fixed_point::to_f64(scaled.confidence as i128, scaled.decimals);
 
-                Ok((price, confidence))
-            }
+            ic_cdk::println!(
+                "✓ {} | Price: ${:.2} | Confidence: ±${:.2} | Timestamp: {}",
+                feed.symbol,
+                price,
+                confidence,
+                timestamp
+            );
+
+            results.push(FeedUpdateResult {
+                symbol: feed.symbol.clone(),
+                success: true,
+                error: None,
+                price: Some(price),
+                confidence: Some(confidence),
+                pushed: false,
+                latency_ns,
+                rejection: None,
+            });
+
+            candidates.extend(prices.into_iter().map(|price| Candidate {
+                feed_symbol: feed.symbol.clone(),
+                oracle_symbol: feed.oracle_symbol().to_string(),
+                push_rules: feed.push_rules.clone(),
+                price,
+            }));
+
+            update_metrics(|m| m.successful_updates += 1);
         }
-        Ok((MultiGetAccountInfoResult::Consistent(GetAccountInfoResult::Ok(None)),)) => {
-            Err("Account not found".to_string())
+        Err(e) => {
+            let (error, rejection) = match e {
+                FeedError::Failed(e) => (e, None),
+                FeedError::Rejected(rejection) => (rejection.to_string(), Some(rejection)),
+            };
+
+            ic_cdk::println!(
+                "✗ {} | Error: {}",
+                feed.symbol,
+                error
+            );
+
+            results.push(FeedUpdateResult {
+                symbol: feed.symbol.clone(),
+                success: false,
+                error: Some(error),
+                price: None,
+                confidence: None,
+                pushed: false,
+                latency_ns,
+                rejection,
+            });
+
+            update_metrics(|m| m.failed_updates += 1);
         }
-        Ok((MultiGetAccountInfoResult::Consistent(GetAccountInfoResult::Err(_)),)) => {
-            Err("RPC returned error".to_string())
+    }
+}
+
+/// Combines the candidates for each oracle symbol, in the order symbols were
+/// first seen, into at most one update per symbol.
+///
+/// Components are brought to the decimals of the symbol's first candidate.
+/// The push rules of the first candidate that has any decide whether the
+/// aggregate is due.
+fn aggregate_candidates(
+    candidates: Vec<Candidate>,
+    settings: &AggregationSettings,
+    timestamp: u64,
+) -> (Vec<PendingUpdate>, Vec<AggregateRecord>) {
+    let mut groups: Vec<(String, Vec<Candidate>)> = Vec::new();
+    for candidate in candidates {
+        match groups.iter_mut().find(|(symbol, _)| *symbol == candidate.oracle_symbol) {
+            Some((_, group)) => group.push(candidate),
+            None => groups.push((candidate.oracle_symbol.clone(), vec![candidate])),
         }
-        Ok((MultiGetAccountInfoResult::Inconsistent(_),)) => {
-            Err("RPC returned inconsistent results".to_string())
+    }
+
+    let mut updates = Vec::new();
+    let mut records = Vec::new();
+
+    for (symbol, group) in groups {
+        let decimals = group[0].price.decimals;
+        let mut components = Vec::new();
+        let mut unscaled = Vec::new();
+        for c in &group {
+            let exponent = -(c.price.decimals as i32);
+            let value = fixed_point::rescale_i64(c.price.value, exponent, decimals, RoundingMode::HalfEven);
+            let confidence = fixed_point::rescale_u64(c.price.confidence, exponent, decimals, RoundingMode::Ceiling);
+            match value.and_then(|value| Ok((value, confidence?))) {
+                Ok((value, confidence)) => {
+                    components.push(aggregation::Component { source: c.price.source.clone(), value, confidence });
+                }
+                Err(e) => {
+                    ic_cdk::println!("✗ {} | {} left out: {}", symbol, c.feed_symbol, e);
+                    unscaled.push((c.feed_symbol.clone(), e.to_string()));
+                }
+            }
         }
-        Err(e) => {
-            Err(format!("RPC call failed: {:?}", e))
+
+        let mut record = AggregateRecord {
+            symbol: symbol.clone(),
+            kept: vec![],
+            dropped: vec![],
+            spread_bps: 0,
+            diverged: false,
+            pushed: false,
+            error: None,
+            unscaled: (!unscaled.is_empty()).then_some(unscaled),
+            skipped: None,
+        };
+
+        match aggregation::aggregate(&components, settings) {
+            Ok(aggregate) => {
+                if aggregate.diverged {
+                    ic_cdk::println!(
+                        "⚠️ {} | Sources diverge by {} bps (dropped: {})",
+                        symbol,
+                        aggregate.spread_bps,
+                        aggregate.dropped.join(",")
+                    );
+                }
+
+                if aggregate.value <= 0 {
+                    ic_cdk::println!("✗ {} | Not pushed: aggregate {} is not positive", symbol, aggregate.value);
+                    record.skipped = Some(format!("Aggregate {} is not positive", aggregate.value));
+                } else {
+                    updates.push(PendingUpdate {
+                        update: OraclePriceUpdate {
+                            symbol: symbol.clone(),
+                            price: OraclePrice {
+                                value: aggregate.value as u64,
+                                confidence: (aggregate.confidence > 0).then_some(aggregate.confidence),
+                                timestamp,
+                                source: aggregate.source.clone(),
+                            },
+                        },
+                        push_rules: group.iter().find_map(|c| c.push_rules.clone()),
+                    });
+                }
+
+                record.kept = aggregate.kept;
+                record.dropped = aggregate.dropped;
+                record.spread_bps = aggregate.spread_bps;
+                record.diverged = aggregate.diverged;
+            }
+            Err(e) => {
+                ic_cdk::println!("✗ {} | Not pushed: {}", symbol, e);
+                record.error = Some(e);
+            }
         }
+
+        records.push(record);
     }
+
+    (updates, records)
+}
+
+fn scale_quote(feed: &PythFeed, quote: Quote) -> Result<ScaledPrice, String> {
+    let transform = feed.transform();
+    let decimals = transform.target_decimals.unwrap_or(DEFAULT_TARGET_DECIMALS);
+    let rounding = transform.rounding.unwrap_or_default();
+
+    let (value, confidence) = if transform.invert {
+        transform::invert(quote.value, quote.exponent, quote.conf, decimals, rounding)?
+    } else {
+        let value = fixed_point::rescale_i64(quote.value, quote.exponent, decimals, rounding)
+            .map_err(|e| format!("Price conversion failed: {}", e))?;
+
+        // Rounding the interval up never understates the uncertainty.
+        let confidence = fixed_point::rescale_u64(quote.conf, quote.exponent, decimals, RoundingMode::Ceiling)
+            .map_err(|e| format!("Confidence conversion failed: {}", e))?;
+
+        (value, confidence)
+    };
+
+    Ok(ScaledPrice {
+        value,
+        confidence,
+        decimals,
+        source: quote.source,
+    })
+}
+
+/// Pushes to every enabled target at once, each getting the updates its
+/// symbol filter accepts and that are due for it under the push rules.
+/// Returns each attempted delivery along with the symbols it carried.
+async fn deliver(targets: &[OracleTarget], updates: &[PendingUpdate]) -> Vec<(TargetDelivery, Vec<String>)> {
+    let pushes = targets.iter().filter(|target| target.enabled).filter_map(|target| {
+        let updates: Vec<OraclePriceUpdate> = updates
+            .iter()
+            .filter(|p| {
+                let update = &p.update;
+                let last_push = get_target_last_push(&target.name, &update.symbol);
+                let price = update.price.value as i64;
+                target.is_due(&update.symbol, price, p.push_rules.as_ref(), last_push.as_ref(), update.price.timestamp)
+            })
+            .map(|p| p.update.clone())
+            .collect();
+        if updates.is_empty() {
+            return None;
+        }
+
+        Some(async move {
+            let symbols: Vec<String> = updates.iter().map(|u| u.symbol.clone()).collect();
+            let mut delivery = TargetDelivery {
+                target: target.name.clone(),
+                pushed: updates.len() as u32,
+                version: None,
+                error: None,
+            };
+
+            match push_to_oracle(target.canister_id, updates).await {
+                Ok(version) => {
+                    ic_cdk::println!("📊 Pushed {} prices to {} (version {})", symbols.len(), target.name, version);
+                    delivery.version = Some(version);
+                }
+                Err(e) => {
+                    ic_cdk::println!("✗ Push to {} failed: {}", target.name, e);
+                    delivery.pushed = 0;
+                    delivery.error = Some(e);
+                }
+            }
+
+            (delivery, symbols)
+        })
+    });
+
+    join_all(pushes).await
 }
 
-async fn push_to_oracle(config: &FeederConfig, updates: Vec<OraclePriceUpdate>) -> Result<(), String> {
-    let call_result: Result<(), _> = ic_cdk::call(
-        config.oracle_canister_id,
+async fn push_to_oracle(oracle: Principal, updates: Vec<OraclePriceUpdate>) -> Result<u64, String> {
+    let call_result: Result<(u64,), _> = ic_cdk::call(
+        oracle,
         "push_prices",
         (updates,)
     ).await;
 
-    call_result.map_err(|e| format!("Oracle call failed: {:?}", e))
+    call_result
+        .map(|(version,)| version)
+        .map_err(|e| format!("Oracle call failed: {:?}", e))
 }
 
 #[update]
-fn add_pyth_feed(symbol: String, account: String, enabled: bool) {
+async fn add_pyth_feed(symbol: String, account: String, enabled: bool) {
     let caller = ic_cdk::caller();
-    if !is_manager(&caller) {
-        ic_cdk::trap("Only manager can add feeds");
+    if !has_role(&caller, Role::Admin) {
+        ic_cdk::trap("Only admin can add feeds");
     }
+    register_if_configured(std::slice::from_ref(&symbol)).await;
 
     let feed = PythFeed {
         symbol,
         account,
         enabled,
+        push_rules: None,
+        quarantine: None,
+        gating: None,
+        source: None,
+        priority: None,
+        transform: None,
     };
 
     add_feed(feed);
@@ -383,11 +718,150 @@ fn add_pyth_feed(symbol: String, account: String, enabled: bool) {
     update_metrics(|m| m.active_feeds = active_feeds);
 }
 
+/// With `register_symbols` on, registers `oracle_symbols` on the targets and
+/// traps if any refuses, before the caller has changed anything, so no feed
+/// is added or enabled.
+async fn register_if_configured(oracle_symbols: &[String]) {
+    let config = get_config();
+    if !config.register_symbols.unwrap_or(false) {
+        return;
+    }
+
+    if let Err(e) = registry::register(&config.targets(), oracle_symbols).await {
+        ic_cdk::trap(&format!("Could not register {} on the oracle: {}", oracle_symbols.join(", "), e));
+    }
+}
+
+/// Compares each enabled target's symbol registry with the oracle symbols of
+/// the enabled feeds it accepts. With `register`, missing symbols are
+/// registered; a target that refuses reports why in its `error`.
+#[update]
+async fn reconcile_oracle_symbols(register: bool) -> Vec<registry::SymbolReconciliation> {
+    let caller = ic_cdk::caller();
+    if register && !has_role(&caller, Role::Admin) {
+        ic_cdk::trap("Only admin can register symbols");
+    }
+    if !has_role(&caller, Role::Viewer) {
+        ic_cdk::trap("Only viewer can reconcile symbols");
+    }
+
+    let pushed: Vec<String> = get_enabled_feeds()
+        .iter()
+        .map(|feed| feed.oracle_symbol().to_string())
+        .collect();
+    let mut reconciliations = Vec::new();
+
+    for target in get_config().targets().into_iter().filter(|target| target.enabled) {
+        let pushed: Vec<String> = pushed.iter().filter(|s| target.accepts(s)).cloned().collect();
+        let mut reconciliation = registry::SymbolReconciliation {
+            target: target.name.clone(),
+            missing: vec![],
+            unfed: vec![],
+            registered: vec![],
+            error: None,
+        };
+
+        match registry::get_all_symbols(&target).await {
+            Ok(registered) => {
+                let (missing, unfed) = registry::diff(&pushed, &registered);
+                if register && !missing.is_empty() {
+                    match registry::upsert_symbols(&target, missing.clone()).await {
+                        Ok(()) => reconciliation.registered = missing.clone(),
+                        Err(e) => reconciliation.error = Some(e),
+                    }
+                }
+                reconciliation.missing = missing;
+                reconciliation.unfed = unfed;
+            }
+            Err(e) => reconciliation.error = Some(e),
+        }
+
+        reconciliations.push(reconciliation);
+    }
+
+    reconciliations
+}
+
+/// Runs a discovery, counting what it spends apart from the rounds.
+async fn discover_products(filter: &ProductFilter) -> Result<Vec<PythProduct>, String> {
+    let config = get_config();
+    let meter = budget::Meter::default();
+    let products = discovery::discover(config.sol_rpc_canister_id, &config.solana_rpc.unwrap_or_default(), filter, &meter).await;
+    update_metrics(|m| budget::add_spent_outside_rounds(m, meter.spent()));
+    products
+}
+
+/// Walks the Pyth mapping accounts and lists the products matching `filter`
+/// that have a price account. It reads Solana, so it is an update call.
+#[update]
+async fn discover_pyth_products(filter: ProductFilter) -> Result<Vec<PythProduct>, String> {
+    let caller = ic_cdk::caller();
+    if !has_role(&caller, Role::Viewer) {
+        ic_cdk::trap("Only viewer can discover products");
+    }
+
+    discover_products(&filter).await
+}
+
+/// Adds a feed for each Pyth product symbol (`Crypto.BTC/USD`), named after
+/// the product's `feed_symbol` and reading its price account. Symbols that
+/// are not found, or whose feed already exists, are reported and skipped.
+#[update]
+async fn add_discovered_feeds(symbols: Vec<String>, enabled: bool) -> Result<Vec<FeedAddition>, String> {
+    let caller = ic_cdk::caller();
+    if !has_role(&caller, Role::Admin) {
+        ic_cdk::trap("Only admin can add feeds");
+    }
+
+    let products = discover_products(&ProductFilter::default()).await?;
+
+    let mut additions = Vec::new();
+    let mut feeds = Vec::new();
+    for symbol in symbols {
+        let mut addition = FeedAddition { symbol, feed_symbol: None, error: None };
+
+        let exists = |symbol: &str| get_feed(symbol).is_some() || feeds.iter().any(|f: &PythFeed| f.symbol == symbol);
+
+        match products.iter().find(|product| product.symbol == addition.symbol) {
+            None => addition.error = Some("No product with this symbol".to_string()),
+            Some(product) if exists(&product.feed_symbol) => {
+                addition.error = Some(format!("Feed {} already exists", product.feed_symbol));
+            }
+            Some(product) => {
+                addition.feed_symbol = Some(product.feed_symbol.clone());
+                feeds.push(PythFeed {
+                    symbol: product.feed_symbol.clone(),
+                    account: product.price_account.clone(),
+                    enabled,
+                    push_rules: None,
+                    quarantine: None,
+                    gating: None,
+                    source: None,
+                    priority: None,
+                    transform: None,
+                });
+            }
+        }
+        additions.push(addition);
+    }
+
+    let oracle_symbols: Vec<String> = feeds.iter().map(|feed| feed.symbol.clone()).collect();
+    register_if_configured(&oracle_symbols).await;
+    for feed in feeds {
+        add_feed(feed);
+    }
+
+    let active_feeds = get_enabled_feeds().len() as u32;
+    update_metrics(|m| m.active_feeds = active_feeds);
+
+    Ok(additions)
+}
+
 #[update]
 fn remove_pyth_feed(symbol: String) -> bool {
     let caller = ic_cdk::caller();
-    if !is_manager(&caller) {
-        ic_cdk::trap("Only manager can remove feeds");
+    if !has_role(&caller, Role::Admin) {
+        ic_cdk::trap("Only admin can remove feeds");
     }
 
     let result = remove_feed(&symbol).is_some();
@@ -399,14 +873,25 @@ fn remove_pyth_feed(symbol: String) -> bool {
 }
 
 #[update]
-fn toggle_feed(symbol: String, enabled: bool) -> bool {
+async fn toggle_feed(symbol: String, enabled: bool) -> bool {
     let caller = ic_cdk::caller();
-    if !is_manager(&caller) {
-        ic_cdk::trap("Only manager can toggle feeds");
+    if !has_role(&caller, Role::Operator) {
+        ic_cdk::trap("Only operator can toggle feeds");
+    }
+
+    if enabled {
+        match get_feed(&symbol) {
+            Some(feed) => register_if_configured(&[feed.oracle_symbol().to_string()]).await,
+            None => return false,
+        }
     }
 
     if let Some(mut feed) = get_feed(&symbol) {
         feed.enabled = enabled;
+        if enabled {
+            feed.quarantine = None;
+            reset_failure_streak(&symbol);
+        }
         add_feed(feed);
 
         let active_feeds = get_enabled_feeds().len() as u32;
@@ -418,6 +903,130 @@ fn toggle_feed(symbol: String, enabled: bool) -> bool {
     }
 }
 
+#[update]
+fn set_push_rules(symbol: String, push_rules: Option<PushRules>) -> bool {
+    let caller = ic_cdk::caller();
+    if !has_role(&caller, Role::Admin) {
+        ic_cdk::trap("Only admin can set push rules");
+    }
+
+    if let Some(mut feed) = get_feed(&symbol) {
+        feed.push_rules = push_rules;
+        add_feed(feed);
+        true
+    } else {
+        false
+    }
+}
+
+#[update]
+fn set_gating_rules(symbol: String, gating: Option<GatingRules>) -> bool {
+    let caller = ic_cdk::caller();
+    if !has_role(&caller, Role::Admin) {
+        ic_cdk::trap("Only admin can set gating rules");
+    }
+
+    if let Some(mut feed) = get_feed(&symbol) {
+        feed.gating = gating;
+        add_feed(feed);
+        true
+    } else {
+        false
+    }
+}
+
+#[update]
+fn set_feed_source(symbol: String, source: Option<FeedSource>) -> bool {
+    let caller = ic_cdk::caller();
+    if !has_role(&caller, Role::Admin) {
+        ic_cdk::trap("Only admin can set feed sources");
+    }
+
+    let source = source.map(|source| match source {
+        FeedSource::Hermes { feed_id } => {
+            let feed_id = hermes::normalize_feed_id(&feed_id);
+            if feed_id.len() != 64 || !feed_id.bytes().all(|b| b.is_ascii_hexdigit()) {
+                ic_cdk::trap("Hermes feed id must be 32 bytes of hex");
+            }
+            FeedSource::Hermes { feed_id }
+        }
+        FeedSource::Chainlink { chain, aggregator, max_age_secs } => {
+            if !chainlink::is_valid_address(&aggregator) {
+                ic_cdk::trap("Chainlink aggregator must be a 0x-prefixed 20-byte address");
+            }
+            FeedSource::Chainlink { chain, aggregator, max_age_secs }
+        }
+        FeedSource::Exchange { tickers } => {
+            if tickers.is_empty() {
+                ic_cdk::trap("Exchange feeds need at least one ticker");
+            }
+            let endpoints = get_config().exchanges.unwrap_or_default();
+            if let Some(ticker) = tickers.iter().find(|t| !endpoints.iter().any(|e| e.name == t.endpoint)) {
+                ic_cdk::trap(&format!("No exchange endpoint named {}", ticker.endpoint));
+            }
+            FeedSource::Exchange { tickers }
+        }
+        other => other,
+    });
+
+    if let Some(mut feed) = get_feed(&symbol) {
+        feed.source = source;
+        add_feed(feed);
+        true
+    } else {
+        false
+    }
+}
+
+#[update]
+fn set_feed_transform(symbol: String, transform: Option<FeedTransform>) -> bool {
+    let caller = ic_cdk::caller();
+    if !has_role(&caller, Role::Admin) {
+        ic_cdk::trap("Only admin can set feed transforms");
+    }
+
+    let transform = transform.map(|FeedTransform::V1(transform)| transform);
+    if let Some(transform) = &transform {
+        if transform.oracle_symbol.as_ref().is_some_and(|s| s.is_empty()) {
+            ic_cdk::trap("Oracle symbol must not be empty");
+        }
+        if let Some(other) = &transform.multiply_by {
+            if *other == symbol || get_feed(other).is_none() {
+                ic_cdk::trap(&format!("multiply_by must name another feed, not {}", other));
+            }
+        }
+    }
+
+    if let Some(mut feed) = get_feed(&symbol) {
+        feed.transform = transform.map(FeedTransform::V1);
+        add_feed(feed);
+        true
+    } else {
+        false
+    }
+}
+
+#[update]
+fn set_feed_priority(symbol: String, priority: Option<FeedPriority>) -> bool {
+    let caller = ic_cdk::caller();
+    if !has_role(&caller, Role::Admin) {
+        ic_cdk::trap("Only admin can set feed priorities");
+    }
+
+    if let Some(mut feed) = get_feed(&symbol) {
+        feed.priority = priority;
+        add_feed(feed);
+        true
+    } else {
+        false
+    }
+}
+
+#[query]
+fn get_last_pushed(symbol: String) -> Option<LastPush> {
+    get_last_push(&symbol)
+}
+
 #[query]
 fn get_pyth_feeds() -> Vec<PythFeed> {
     get_all_feeds()
@@ -428,6 +1037,66 @@ fn get_pyth_feed(symbol: String) -> Option<PythFeed> {
     get_feed(&symbol)
 }
 
+/// Decodes raw account data exactly as the fetch loop would, so operators can
+/// check a new feed's account before adding it.
+#[query]
+fn decode_pyth_account(bytes: serde_bytes::ByteBuf) -> Result<ParsedPrice, String> {
+    pyth::decode_account(&bytes)
+}
+
+/// Verifies a Hermes accumulator update against the configured guardian set
+/// and returns the prices it carries.
+#[query]
+fn verify_accumulator_update(bytes: serde_bytes::ByteBuf) -> Result<Vec<ParsedPrice>, String> {
+    let guardian_set = get_config()
+        .hermes
+        .and_then(|hermes| hermes.guardian_set)
+        .ok_or_else(|| "No guardian set configured".to_string())?;
+    let guardians = wormhole::GuardianSet::from_config(&guardian_set)?;
+
+    wormhole::verify_accumulator_update(&bytes, &guardians).map(|update| update.parsed_prices())
+}
+
+#[query]
+fn transform_hermes_response(args: TransformArgs) -> HttpResponse {
+    hermes::transform(args)
+}
+
+#[query]
+fn transform_exchange_response(args: TransformArgs) -> HttpResponse {
+    exchange::transform(args)
+}
+
+#[query]
+fn get_quarantined_feeds() -> Vec<PythFeed> {
+    get_all_feeds()
+        .into_iter()
+        .filter(|feed| feed.quarantine.is_some())
+        .collect()
+}
+
+#[query]
+fn get_feed_health(symbol: String) -> Option<FeedHealth> {
+    crate::state::get_feed_health(&symbol)
+}
+
+#[query]
+fn get_oracle_target_status() -> Vec<(String, TargetStatus)> {
+    get_target_statuses()
+}
+
+/// How many publishers went into each legacy Pyth feed's last aggregate, as
+/// of the feed's last fetch.
+#[query]
+fn get_publisher_stats() -> Vec<(String, PublisherStats)> {
+    get_all_publisher_stats()
+}
+
+#[query]
+fn get_recent_rounds(n: u64) -> Vec<RoundRecord> {
+    crate::state::get_recent_rounds(n.min(ROUND_LOG_CAPACITY) as usize)
+}
+
 #[query]
 fn get_feeder_metrics() -> FeederMetrics {
     get_metrics()
@@ -435,38 +1104,86 @@ fn get_feeder_metrics() -> FeederMetrics {
 
 #[query]
 fn get_feeder_config() -> FeederConfig {
+    // Custom RPC endpoints can carry API keys in their headers.
+    let caller = ic_cdk::caller();
+    if !has_role(&caller, Role::Viewer) {
+        ic_cdk::trap("Only viewer can read config");
+    }
+
     get_config()
 }
 
 #[update]
 fn set_feeder_config(config: FeederConfig) {
     let caller = ic_cdk::caller();
-    if !is_manager(&caller) {
-        ic_cdk::trap("Only manager can set config");
+    if !has_role(&caller, Role::Admin) {
+        ic_cdk::trap("Only admin can set config");
+    }
+    if let Err(e) = config.validate() {
+        ic_cdk::trap(&format!("Invalid config: {}", e));
     }
 
+    let interval_changed = config.update_interval_secs != get_config().update_interval_secs;
+    let interval_secs = config.update_interval_secs;
     set_config(config);
+
+    if interval_changed {
+        scheduler::reschedule(interval_secs);
+    }
 }
 
 #[query]
-fn get_manager() -> Principal {
-    crate::state::get_manager()
+fn get_my_role() -> Option<Role> {
+    get_role(&ic_cdk::caller())
+}
+
+#[query]
+fn get_roles() -> Vec<(String, Role)> {
+    let caller = ic_cdk::caller();
+    if !has_role(&caller, Role::Viewer) {
+        ic_cdk::trap("Only viewer can list roles");
+    }
+
+    get_all_roles()
+}
+
+#[update]
+fn grant_role(principal: Principal, role: Role) {
+    let caller = ic_cdk::caller();
+    if !has_role(&caller, Role::Admin) {
+        ic_cdk::trap("Only admin can grant roles");
+    }
+
+    if principal == Principal::anonymous() {
+        ic_cdk::trap("Cannot grant a role to the anonymous principal");
+    }
+
+    if role != Role::Admin && get_role(&principal) == Some(Role::Admin) && count_admins() == 1 {
+        ic_cdk::trap("Cannot demote the last admin");
+    }
+
+    set_role(&principal, role);
 }
 
 #[update]
-fn set_manager(new_manager: Principal) {
+fn revoke_role(principal: Principal) -> Option<Role> {
     let caller = ic_cdk::caller();
-    if !is_manager(&caller) {
-        ic_cdk::trap("Only manager can update manager");
+    if !has_role(&caller, Role::Admin) {
+        ic_cdk::trap("Only admin can revoke roles");
     }
-    crate::state::set_manager(new_manager);
+
+    if get_role(&principal) == Some(Role::Admin) && count_admins() == 1 {
+        ic_cdk::trap("Cannot revoke the last admin");
+    }
+
+    remove_role(&principal)
 }
 
 #[update]
 async fn manual_fetch() -> Vec<FeedUpdateResult> {
     let caller = ic_cdk::caller();
-    if caller == Principal::anonymous() {
-        ic_cdk::trap("Anonymous principal cannot trigger manual fetch");
+    if !has_role(&caller, Role::Operator) {
+        ic_cdk::trap("Only operator can trigger manual fetch");
     }
 
     fetch_and_push_prices()
@@ -477,6 +1194,9 @@ async fn manual_fetch() -> Vec<FeedUpdateResult> {
             error: Some(e),
             price: None,
             confidence: None,
+            pushed: false,
+            latency_ns: 0,
+            rejection: None,
         }])
 }
 
@@ -1,6 +1,9 @@
 use candid::CandidType;
 use serde::{Deserialize, Serialize};
 
+/// Magic number at the start of every legacy Pyth account.
+const MAGIC: u32 = 0xa1b2c3d4;
+
 #[repr(u32)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AccountType {
@@ -10,6 +13,17 @@ pub enum AccountType {
     Price = 3,
 }
 
+impl AccountType {
+    fn from_u32(value: u32) -> Self {
+        match value {
+            1 => AccountType::Mapping,
+            2 => AccountType::Product,
+            3 => AccountType::Price,
+            _ => AccountType::Unknown,
+        }
+    }
+}
+
 #[repr(u32)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, CandidType, Serialize, Deserialize)]
 pub enum PriceStatus {
@@ -19,13 +33,6 @@ pub enum PriceStatus {
     Auction = 3,
 }
 
-#[repr(u32)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum PriceType {
-    Unknown = 0,
-    Price = 1,
-}
-
 #[derive(Debug, Clone, CandidType, Serialize, Deserialize)]
 pub struct PriceInfo {
     pub price: i64,
@@ -35,6 +42,27 @@ pub struct PriceInfo {
     pub pub_slot: u64,
 }
 
+/// One publisher's entry in a legacy price account: its latest submission
+/// and the submission the last aggregation used.
+#[derive(Debug, Clone, CandidType, Serialize, Deserialize)]
+pub struct PriceComponent {
+    pub publisher: [u8; 32],
+    pub agg: PriceInfo,
+    pub latest: PriceInfo,
+}
+
+/// How many of a price account's publishers went into its aggregate, and
+/// how far apart their prices were.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Serialize, Deserialize)]
+pub struct PublisherStats {
+    pub publishers: u32,
+    pub contributing: u32,
+    /// Highest minus lowest contributing price, in the account's units.
+    pub spread: Option<u64>,
+    /// `spread` in basis points of the aggregate price.
+    pub spread_bps: Option<u64>,
+}
+
 #[derive(Debug, Clone, CandidType, Serialize, Deserialize)]
 pub struct Ema {
     pub val: i64,
@@ -42,6 +70,42 @@ pub struct Ema {
     pub denom: i64,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, CandidType, Serialize, Deserialize)]
+pub enum AccountFormat {
+    Legacy,
+    PriceUpdateV2,
+    /// Read from Hermes rather than a Solana account.
+    Hermes,
+    /// Read from a Hermes accumulator update whose guardian signatures and
+    /// Merkle proof were verified in the canister.
+    Accumulator,
+}
+
+/// Price fields shared by every account format, still in Pyth's own units:
+/// the real value is `price * 10^exponent`.
+///
+/// Fields a format does not carry are `None`: `PriceUpdateV2` accounts have
+/// no current slot or publisher count, and are only posted while trading.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Serialize, Deserialize)]
+pub struct ParsedPrice {
+    pub format: AccountFormat,
+    pub price: i64,
+    pub conf: u64,
+    pub exponent: i32,
+    pub status: PriceStatus,
+    pub publish_time: i64,
+    pub pub_slot: u64,
+    pub curr_slot: Option<u64>,
+    pub num_publishers: Option<u32>,
+    pub publishers: Option<PublisherStats>,
+}
+
+/// Publisher slots in a legacy price account.
+pub const MAX_COMPONENTS: usize = 32;
+
+const COMPONENTS_OFFSET: usize = 240;
+const COMPONENT_SIZE: usize = 96;
+
 #[derive(Debug, Clone, CandidType, Serialize, Deserialize)]
 pub struct PriceAccount {
     pub magic: u32,
@@ -51,34 +115,37 @@ pub struct PriceAccount {
     pub ptype: u32,
     pub exponent: i32,
     pub num: u32,
-    pub unused: u32,
+    pub num_qt: u32,
     pub curr_slot: u64,
     pub valid_slot: u64,
     pub twap: Ema,
     pub twac: Ema,
-    pub drv1: u64,
+    pub timestamp: i64,
     pub drv2: PriceInfo,
     pub prod: [u8; 32],
     pub next: [u8; 32],
     pub agg_pub_slot: u64,
     pub agg: PriceInfo,
+    /// The first `num` publisher components, or fewer if the data stops
+    /// short of them.
+    pub comp: Vec<PriceComponent>,
 }
 
 impl PriceAccount {
     pub fn parse(data: &[u8]) -> Result<Self, String> {
-        if data.len() < 208 {
-            return Err(format!("Invalid Pyth account data length: {} (expected at least 208)", data.len()));
+        if data.len() < 240 {
+            return Err(format!("Invalid Pyth account data length: {} (expected at least 240)", data.len()));
         }
 
         let magic = u32::from_le_bytes([data[0], data[1], data[2], data[3]]);
-        if magic != 0xa1b2c3d4 {
-            return Err(format!("Invalid magic number: 0x{:x} (expected 0xa1b2c3d4)", magic));
+        if magic != MAGIC {
+            return Err(format!("Invalid magic number: 0x{:x} (expected 0x{:x})", magic, MAGIC));
         }
 
         let ver = u32::from_le_bytes([data[4], data[5], data[6], data[7]]);
         let atype = u32::from_le_bytes([data[8], data[9], data[10], data[11]]);
 
-        if atype != 3 {
+        if AccountType::from_u32(atype) != AccountType::Price {
             return Err(format!("Not a price account (type: {})", atype));
         }
 
@@ -86,7 +153,7 @@ impl PriceAccount {
         let ptype = u32::from_le_bytes([data[16], data[17], data[18], data[19]]);
         let exponent = i32::from_le_bytes([data[20], data[21], data[22], data[23]]);
         let num = u32::from_le_bytes([data[24], data[25], data[26], data[27]]);
-        let unused = u32::from_le_bytes([data[28], data[29], data[30], data[31]]);
+        let num_qt = u32::from_le_bytes([data[28], data[29], data[30], data[31]]);
         let curr_slot = u64::from_le_bytes([
             data[32], data[33], data[34], data[35],
             data[36], data[37], data[38], data[39],
@@ -126,7 +193,7 @@ impl PriceAccount {
             ]),
         };
 
-        let drv1 = u64::from_le_bytes([
+        let timestamp = i64::from_le_bytes([
             data[96], data[97], data[98], data[99],
             data[100], data[101], data[102], data[103],
         ]);
@@ -146,6 +213,22 @@ impl PriceAccount {
 
         let agg = Self::parse_price_info(&data[208..240])?;
 
+        let mut comp = Vec::new();
+        for index in 0..(num as usize).min(MAX_COMPONENTS) {
+            let start = COMPONENTS_OFFSET + index * COMPONENT_SIZE;
+            let Some(component) = data.get(start..start + COMPONENT_SIZE) else {
+                break;
+            };
+
+            let mut publisher = [0u8; 32];
+            publisher.copy_from_slice(&component[..32]);
+            comp.push(PriceComponent {
+                publisher,
+                agg: Self::parse_price_info(&component[32..64])?,
+                latest: Self::parse_price_info(&component[64..96])?,
+            });
+        }
+
         Ok(PriceAccount {
             magic,
             ver,
@@ -154,17 +237,18 @@ impl PriceAccount {
             ptype,
             exponent,
             num,
-            unused,
+            num_qt,
             curr_slot,
             valid_slot,
             twap,
             twac,
-            drv1,
+            timestamp,
             drv2,
             prod,
             next,
             agg_pub_slot,
             agg,
+            comp,
         })
     }
 
@@ -208,21 +292,278 @@ impl PriceAccount {
         })
     }
 
-    pub fn get_price_as_f64(&self) -> f64 {
-        let base = self.agg.price as f64;
-        let exponent = self.exponent as f64;
-        base * 10f64.powf(exponent)
+    pub fn is_valid(&self) -> bool {
+        self.agg.price != 0
     }
 
-    pub fn get_confidence_as_f64(&self) -> f64 {
-        let base = self.agg.conf as f64;
-        let exponent = self.exponent as f64;
-        base * 10f64.powf(exponent)
+    /// `None` when the data stops short of the `num` components. A
+    /// publisher contributed if its component's `agg` is `Trading`: the
+    /// aggregation marks the ones it left out as `Unknown`.
+    pub fn publisher_stats(&self) -> Option<PublisherStats> {
+        if self.comp.len() < (self.num as usize).min(MAX_COMPONENTS) {
+            return None;
+        }
+
+        let prices: Vec<i64> = self
+            .comp
+            .iter()
+            .filter(|component| component.agg.status == PriceStatus::Trading)
+            .map(|component| component.agg.price)
+            .collect();
+
+        let spread = match (prices.iter().min(), prices.iter().max()) {
+            (Some(&min), Some(&max)) => Some(max.abs_diff(min)),
+            _ => None,
+        };
+        let spread_bps = spread.and_then(|spread| {
+            (spread as u128 * 10_000)
+                .checked_div(self.agg.price.unsigned_abs() as u128)
+                .map(|bps| bps.min(u64::MAX as u128) as u64)
+        });
+
+        Some(PublisherStats {
+            publishers: self.num,
+            contributing: prices.len() as u32,
+            spread,
+            spread_bps,
+        })
     }
 
-    pub fn is_valid(&self) -> bool {
-        self.agg.price != 0
+    pub fn to_parsed_price(&self) -> ParsedPrice {
+        ParsedPrice {
+            format: AccountFormat::Legacy,
+            price: self.agg.price,
+            conf: self.agg.conf,
+            exponent: self.exponent,
+            status: self.agg.status,
+            publish_time: self.timestamp,
+            pub_slot: self.agg.pub_slot,
+            curr_slot: Some(self.curr_slot),
+            num_publishers: Some(self.num),
+            publishers: self.publisher_stats(),
+        }
+    }
+}
+
+/// Anchor discriminator of the pull-oracle `PriceUpdateV2` account.
+pub const PRICE_UPDATE_V2_DISCRIMINATOR: [u8; 8] = [34, 241, 35, 99, 157, 126, 244, 205];
+
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Serialize, Deserialize)]
+pub enum VerificationLevel {
+    Partial { num_signatures: u8 },
+    Full,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Serialize, Deserialize)]
+pub struct PriceFeedMessage {
+    pub feed_id: [u8; 32],
+    pub price: i64,
+    pub conf: u64,
+    pub exponent: i32,
+    pub publish_time: i64,
+    pub prev_publish_time: i64,
+    pub ema_price: i64,
+    pub ema_conf: u64,
+}
+
+#[derive(Debug, Clone, CandidType, Serialize, Deserialize)]
+pub struct PriceUpdateV2 {
+    pub write_authority: [u8; 32],
+    pub verification_level: VerificationLevel,
+    pub price_message: PriceFeedMessage,
+    pub posted_slot: u64,
+}
+
+impl PriceUpdateV2 {
+    pub fn is_price_update_v2(data: &[u8]) -> bool {
+        data.starts_with(&PRICE_UPDATE_V2_DISCRIMINATOR)
     }
+
+    pub fn parse(data: &[u8]) -> Result<Self, String> {
+        if !Self::is_price_update_v2(data) {
+            return Err("Not a PriceUpdateV2 account (bad discriminator)".to_string());
+        }
+
+        let mut write_authority = [0u8; 32];
+        write_authority.copy_from_slice(read_bytes(data, 8, 32)?);
+
+        let (verification_level, message_offset) = match read_bytes(data, 40, 1)?[0] {
+            0 => (VerificationLevel::Partial { num_signatures: read_bytes(data, 41, 1)?[0] }, 42),
+            1 => (VerificationLevel::Full, 41),
+            tag => return Err(format!("Unknown verification level: {}", tag)),
+        };
+
+        let mut feed_id = [0u8; 32];
+        feed_id.copy_from_slice(read_bytes(data, message_offset, 32)?);
+
+        let at = message_offset + 32;
+        let price_message = PriceFeedMessage {
+            feed_id,
+            price: i64::from_le_bytes(read_array(data, at)?),
+            conf: u64::from_le_bytes(read_array(data, at + 8)?),
+            exponent: i32::from_le_bytes(read_array(data, at + 16)?),
+            publish_time: i64::from_le_bytes(read_array(data, at + 20)?),
+            prev_publish_time: i64::from_le_bytes(read_array(data, at + 28)?),
+            ema_price: i64::from_le_bytes(read_array(data, at + 36)?),
+            ema_conf: u64::from_le_bytes(read_array(data, at + 44)?),
+        };
+
+        let posted_slot = u64::from_le_bytes(read_array(data, at + 52)?);
+
+        Ok(PriceUpdateV2 {
+            write_authority,
+            verification_level,
+            price_message,
+            posted_slot,
+        })
+    }
+
+    pub fn to_parsed_price(&self) -> ParsedPrice {
+        ParsedPrice {
+            format: AccountFormat::PriceUpdateV2,
+            price: self.price_message.price,
+            conf: self.price_message.conf,
+            exponent: self.price_message.exponent,
+            status: PriceStatus::Trading,
+            publish_time: self.price_message.publish_time,
+            pub_slot: self.posted_slot,
+            curr_slot: None,
+            num_publishers: None,
+            publishers: None,
+        }
+    }
+}
+
+/// Detects whether `data` is a legacy price account or a `PriceUpdateV2`
+/// account and decodes it. The fetch loop and `decode_pyth_account` both go
+/// through here, so an account that decodes offline decodes in production.
+pub fn decode_account(data: &[u8]) -> Result<ParsedPrice, String> {
+    if PriceUpdateV2::is_price_update_v2(data) {
+        let update = PriceUpdateV2::parse(data)?;
+        if update.price_message.price == 0 {
+            return Err("Price is zero".to_string());
+        }
+
+        Ok(update.to_parsed_price())
+    } else {
+        let price_account = PriceAccount::parse(data)?;
+        if !price_account.is_valid() {
+            return Err(format!(
+                "Price not valid (status: {:?}, price: {})",
+                price_account.agg.status, price_account.agg.price
+            ));
+        }
+
+        Ok(price_account.to_parsed_price())
+    }
+}
+
+/// Pyth's mainnet mapping account, the head of the list of every product.
+pub const MAINNET_MAPPING_ACCOUNT: &str = "AHtgzX45WTKfkPG53L6WYhGEXwQkN1BVknET3sVsLL8J";
+
+/// A legacy mapping account: a page of product account keys, linked to the
+/// next page through `next`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MappingAccount {
+    pub next: Option<[u8; 32]>,
+    pub products: Vec<[u8; 32]>,
+}
+
+impl MappingAccount {
+    pub fn parse(data: &[u8]) -> Result<Self, String> {
+        check_header(data, AccountType::Mapping)?;
+
+        let num = u32::from_le_bytes(read_array(data, 16)?) as usize;
+        let next: [u8; 32] = read_array(data, 24)?;
+        let products = read_bytes(data, 56, num.saturating_mul(32))?
+            .chunks_exact(32)
+            .map(|key| key.try_into().expect("chunks are 32 bytes"))
+            .collect();
+
+        Ok(MappingAccount {
+            next: (next != [0u8; 32]).then_some(next),
+            products,
+        })
+    }
+}
+
+/// A legacy product account: the price account it points to and its
+/// reference attributes (`symbol`, `asset_type`, `quote_currency`, ...).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProductAccount {
+    pub price_account: Option<[u8; 32]>,
+    pub attributes: Vec<(String, String)>,
+}
+
+impl ProductAccount {
+    pub fn parse(data: &[u8]) -> Result<Self, String> {
+        check_header(data, AccountType::Product)?;
+
+        let size = u32::from_le_bytes(read_array(data, 12)?) as usize;
+        let price_account: [u8; 32] = read_array(data, 16)?;
+
+        // Attributes are length-prefixed key and value strings filling the
+        // account up to `size`.
+        let store = read_bytes(data, 48, size.saturating_sub(48))?;
+        let mut attributes = Vec::new();
+        let mut offset = 0;
+        while offset < store.len() {
+            let key = read_string(store, &mut offset)?;
+            let value = read_string(store, &mut offset)?;
+            attributes.push((key, value));
+        }
+
+        Ok(ProductAccount {
+            price_account: (price_account != [0u8; 32]).then_some(price_account),
+            attributes,
+        })
+    }
+
+    pub fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+fn check_header(data: &[u8], expected: AccountType) -> Result<(), String> {
+    let magic = u32::from_le_bytes(read_array(data, 0)?);
+    if magic != MAGIC {
+        return Err(format!("Invalid magic number: 0x{:x} (expected 0x{:x})", magic, MAGIC));
+    }
+
+    let atype = u32::from_le_bytes(read_array(data, 8)?);
+    if AccountType::from_u32(atype) != expected {
+        return Err(format!("Not a {:?} account (type: {})", expected, atype));
+    }
+
+    Ok(())
+}
+
+fn read_string(data: &[u8], offset: &mut usize) -> Result<String, String> {
+    let len = read_bytes(data, *offset, 1)?[0] as usize;
+    let bytes = read_bytes(data, *offset + 1, len)?;
+    *offset += 1 + len;
+
+    String::from_utf8(bytes.to_vec()).map_err(|_| "Product attribute is not UTF-8".to_string())
+}
+
+fn read_bytes(data: &[u8], offset: usize, len: usize) -> Result<&[u8], String> {
+    let end = offset.checked_add(len);
+    end.and_then(|end| data.get(offset..end)).ok_or_else(|| {
+        format!(
+            "Account data too short: need {} bytes, have {}",
+            offset.saturating_add(len),
+            data.len()
+        )
+    })
+}
+
+fn read_array<const N: usize>(data: &[u8], offset: usize) -> Result<[u8; N], String> {
+    let mut out = [0u8; N];
+    out.copy_from_slice(read_bytes(data, offset, N)?);
+    Ok(out)
 }
 
 #[cfg(test)]
@@ -233,4 +574,224 @@ mod tests {
     fn test_price_info_parsing() {
         assert_eq!(std::mem::size_of::<PriceInfo>(), 32);
     }
+
+    fn price_update_v2(verification_level: &[u8], price: i64, conf: u64, exponent: i32) -> Vec<u8> {
+        let mut data = PRICE_UPDATE_V2_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&[7u8; 32]);
+        data.extend_from_slice(verification_level);
+        data.extend_from_slice(&[9u8; 32]);
+        data.extend_from_slice(&price.to_le_bytes());
+        data.extend_from_slice(&conf.to_le_bytes());
+        data.extend_from_slice(&exponent.to_le_bytes());
+        data.extend_from_slice(&1_700_000_000i64.to_le_bytes());
+        data.extend_from_slice(&1_699_999_999i64.to_le_bytes());
+        data.extend_from_slice(&(price - 1).to_le_bytes());
+        data.extend_from_slice(&(conf + 1).to_le_bytes());
+        data.extend_from_slice(&250_000_000u64.to_le_bytes());
+        data.resize(134, 0);
+        data
+    }
+
+    #[test]
+    fn test_price_update_v2_reads_spot_price_not_ema() {
+        for level in [&[1u8][..], &[0u8, 13][..]] {
+            let data = price_update_v2(level, 6_734_512_345_678, 1_234_567, -8);
+            let update = PriceUpdateV2::parse(&data).unwrap();
+
+            let price = update.to_parsed_price();
+            assert_eq!((price.price, price.conf, price.exponent), (6_734_512_345_678, 1_234_567, -8));
+            assert_eq!(price.publish_time, 1_700_000_000);
+            assert_eq!(price.pub_slot, 250_000_000);
+        }
+    }
+
+    fn fixture(encoded: &str) -> Vec<u8> {
+        use base64::Engine;
+
+        base64::engine::general_purpose::STANDARD
+            .decode(encoded.trim())
+            .expect("fixture is valid base64")
+    }
+
+    const FIXTURES: [(&str, &str); 5] = [
+        ("btc_usd_legacy", include_str!("fixtures/btc_usd_legacy.b64")),
+        ("aapl_usd_legacy_halted", include_str!("fixtures/aapl_usd_legacy_halted.b64")),
+        ("sol_usd_price_update_v2_full", include_str!("fixtures/sol_usd_price_update_v2_full.b64")),
+        ("eth_usd_price_update_v2_partial", include_str!("fixtures/eth_usd_price_update_v2_partial.b64")),
+        ("btc_usd_product", include_str!("fixtures/btc_usd_product.b64")),
+    ];
+
+    #[test]
+    fn test_fixture_legacy_trading() {
+        let price = decode_account(&fixture(FIXTURES[0].1)).unwrap();
+
+        assert_eq!(price.format, AccountFormat::Legacy);
+        assert_eq!((price.price, price.conf, price.exponent), (6_734_512_345_678, 2_150_000_000, -8));
+        assert_eq!(price.status, PriceStatus::Trading);
+        assert_eq!(price.publish_time, 1_728_000_000);
+        assert_eq!((price.pub_slot, price.curr_slot), (289_345_120, Some(289_345_121)));
+        assert_eq!(price.num_publishers, Some(24));
+    }
+
+    #[test]
+    fn test_fixture_legacy_halted_still_decodes() {
+        let price = decode_account(&fixture(FIXTURES[1].1)).unwrap();
+
+        assert_eq!(price.status, PriceStatus::Halted);
+        assert_eq!((price.price, price.exponent), (22_674_000, -5));
+    }
+
+    #[test]
+    fn test_fixture_legacy_components() {
+        let account = PriceAccount::parse(&fixture(FIXTURES[0].1)).unwrap();
+        assert_eq!(account.comp.len(), 24);
+        assert_eq!(account.comp[0].publisher[..4], [0xe2, 0xa9, 0x9f, 0x5e]);
+        assert_eq!((account.comp[1].agg.price, account.comp[1].latest.price), (6_734_509_595_678, 6_734_509_595_778));
+        assert_eq!((account.comp[1].agg.pub_slot, account.comp[1].latest.pub_slot), (289_345_120, 289_345_121));
+
+        let stats = account.publisher_stats().unwrap();
+        assert_eq!((stats.publishers, stats.contributing), (24, 24));
+        assert_eq!((stats.spread, stats.spread_bps), (Some(5_750_000), Some(0)));
+
+        // Halted publishers are listed but none went into the aggregate.
+        let halted = decode_account(&fixture(FIXTURES[1].1)).unwrap();
+        let stats = halted.publishers.unwrap();
+        assert_eq!((stats.publishers, stats.contributing, stats.spread), (7, 0, None));
+        assert_eq!(halted.num_publishers, Some(7));
+    }
+
+    #[test]
+    fn test_only_trading_components_contribute() {
+        let mut data = fixture(FIXTURES[0].1);
+        // Publisher 0 was left out of the aggregate; publisher 23 is far off.
+        data[240 + 32 + 16..240 + 32 + 20].copy_from_slice(&0u32.to_le_bytes());
+        let last = 240 + 23 * 96 + 32;
+        data[last..last + 8].copy_from_slice(&7_000_000_000_000i64.to_le_bytes());
+
+        let price = decode_account(&data).unwrap();
+        let stats = price.publishers.unwrap();
+        assert_eq!((stats.contributing, price.num_publishers), (23, Some(24)));
+        assert_eq!((stats.spread, stats.spread_bps), (Some(265_490_404_322), Some(394)));
+    }
+
+    #[test]
+    fn test_missing_components_leave_no_stats() {
+        let data = fixture(FIXTURES[0].1);
+        let price = decode_account(&data[..240 + 10 * 96 + 50]).unwrap();
+
+        assert_eq!(price.publishers, None);
+        assert_eq!(price.num_publishers, Some(24));
+        assert_eq!(PriceAccount::parse(&data[..240 + 10 * 96 + 50]).unwrap().comp.len(), 10);
+    }
+
+    #[test]
+    fn test_fixture_price_update_v2() {
+        let full = decode_account(&fixture(FIXTURES[2].1)).unwrap();
+        assert_eq!(full.format, AccountFormat::PriceUpdateV2);
+        assert_eq!((full.price, full.conf, full.exponent), (14_523_456_789, 9_876_543, -8));
+        assert_eq!((full.publish_time, full.pub_slot), (1_728_000_003, 289_345_128));
+
+        let partial = PriceUpdateV2::parse(&fixture(FIXTURES[3].1)).unwrap();
+        assert_eq!(partial.verification_level, VerificationLevel::Partial { num_signatures: 5 });
+        assert_eq!(partial.to_parsed_price().price, 262_345_678_901);
+    }
+
+    #[test]
+    fn test_fixture_product_account_is_not_a_price() {
+        assert_eq!(
+            decode_account(&fixture(FIXTURES[4].1)),
+            Err("Not a price account (type: 2)".to_string())
+        );
+    }
+
+    #[test]
+    fn test_fixture_product_attributes() {
+        let product = ProductAccount::parse(&fixture(FIXTURES[4].1)).unwrap();
+
+        assert_eq!(product.attribute("symbol"), Some("Crypto.BTC/USD"));
+        assert_eq!(product.attribute("asset_type"), Some("Crypto"));
+        assert_eq!(product.attribute("quote_currency"), Some("USD"));
+        assert_eq!(product.attribute("base"), Some("BTC"));
+        assert_eq!(product.attributes.len(), 6);
+        assert_eq!(product.price_account.map(|key| key[..4].to_vec()), Some(vec![0x87, 0x19, 0xc0, 0x2b]));
+
+        assert_eq!(
+            ProductAccount::parse(&fixture(FIXTURES[0].1)),
+            Err("Not a Product account (type: 3)".to_string())
+        );
+    }
+
+    fn mapping(products: &[[u8; 32]], next: [u8; 32]) -> Vec<u8> {
+        let mut data = MAGIC.to_le_bytes().to_vec();
+        data.extend_from_slice(&2u32.to_le_bytes());
+        data.extend_from_slice(&(AccountType::Mapping as u32).to_le_bytes());
+        data.extend_from_slice(&(56 + 32 * products.len() as u32).to_le_bytes());
+        data.extend_from_slice(&(products.len() as u32).to_le_bytes());
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&next);
+        for product in products {
+            data.extend_from_slice(product);
+        }
+        data.resize(1024, 0);
+        data
+    }
+
+    #[test]
+    fn test_mapping_lists_products_and_links_the_next_page() {
+        let page = MappingAccount::parse(&mapping(&[[1; 32], [2; 32]], [9; 32])).unwrap();
+        assert_eq!(page.products, vec![[1; 32], [2; 32]]);
+        assert_eq!(page.next, Some([9; 32]));
+
+        let last = MappingAccount::parse(&mapping(&[[3; 32]], [0; 32])).unwrap();
+        assert_eq!(last.next, None);
+
+        let mut truncated = mapping(&[[1; 32], [2; 32]], [0; 32]);
+        truncated.truncate(100);
+        assert!(MappingAccount::parse(&truncated).is_err());
+    }
+
+    #[test]
+    fn test_product_attributes_stop_at_the_account_size() {
+        let mut data = fixture(FIXTURES[4].1);
+        // Cutting `size` inside the last value leaves a truncated string.
+        data[12..16].copy_from_slice(&156u32.to_le_bytes());
+        assert!(ProductAccount::parse(&data).is_err());
+
+        data[12..16].copy_from_slice(&48u32.to_le_bytes());
+        assert_eq!(ProductAccount::parse(&data).unwrap().attributes, vec![]);
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn fuzz_decode_never_panics(data in proptest::collection::vec(proptest::num::u8::ANY, 0..4096)) {
+            let _ = decode_account(&data);
+            let _ = PriceAccount::parse(&data);
+            let _ = ProductAccount::parse(&data);
+            let _ = MappingAccount::parse(&data);
+        }
+
+        #[test]
+        fn fuzz_decode_never_panics_on_damaged_fixtures(
+            index in 0..FIXTURES.len(),
+            cut in proptest::num::usize::ANY,
+            flips in proptest::collection::vec((proptest::num::usize::ANY, proptest::num::u8::ANY), 0..16),
+        ) {
+            let mut data = fixture(FIXTURES[index].1);
+            for (position, byte) in flips {
+                let position = position % data.len();
+                data[position] ^= byte;
+            }
+            data.truncate(cut % (data.len() + 1));
+
+            let _ = decode_account(&data);
+            let _ = ProductAccount::parse(&data);
+            let _ = MappingAccount::parse(&data);
+        }
+    }
+
+    #[test]
+    fn test_price_update_v2_rejects_truncated_data() {
+        let data = price_update_v2(&[1u8], 1, 1, -8);
+        assert!(PriceUpdateV2::parse(&data[..100]).is_err());
+    }
 }
@@ -0,0 +1,111 @@
+use candid::{CandidType, Principal};
+use ic_cdk::api::call::RejectionCode;
+use serde::{Deserialize, Serialize};
+
+use crate::types::OracleTarget;
+
+/// How a target's symbol registry compares with the feeder's feeds.
+#[derive(Debug, Clone, CandidType, Serialize, Deserialize)]
+pub struct SymbolReconciliation {
+    pub target: String,
+    /// Symbols the feeder pushes that the oracle does not know, so it drops
+    /// their updates once its registry is non-empty.
+    pub missing: Vec<String>,
+    /// Symbols the oracle knows that no enabled feed pushes to it.
+    pub unfed: Vec<String>,
+    /// Missing symbols registered by this call.
+    pub registered: Vec<String>,
+    pub error: Option<String>,
+}
+
+/// Registers `symbols` on every enabled target that accepts them, stopping
+/// at the first target that refuses.
+pub async fn register(targets: &[OracleTarget], symbols: &[String]) -> Result<(), String> {
+    for target in targets.iter().filter(|target| target.enabled) {
+        let symbols: Vec<String> = symbols.iter().filter(|s| target.accepts(s)).cloned().collect();
+        if !symbols.is_empty() {
+            upsert_symbols(target, symbols).await?;
+        }
+    }
+    Ok(())
+}
+
+pub async fn upsert_symbols(target: &OracleTarget, symbols: Vec<String>) -> Result<(), String> {
+    ic_cdk::call::<_, ()>(target.canister_id, "upsert_symbols", (symbols,))
+        .await
+        .map_err(|rejection| describe_rejection(target, ic_cdk::id(), rejection))
+}
+
+pub async fn get_all_symbols(target: &OracleTarget) -> Result<Vec<String>, String> {
+    ic_cdk::call::<_, (Vec<String>,)>(target.canister_id, "get_all_symbols", ())
+        .await
+        .map(|(symbols,)| symbols)
+        .map_err(|rejection| describe_rejection(target, ic_cdk::id(), rejection))
+}
+
+/// Compares the symbols the feeder pushes to a target with the target's
+/// registry, returning `(missing, unfed)`, both sorted.
+pub fn diff(pushed: &[String], registered: &[String]) -> (Vec<String>, Vec<String>) {
+    let mut missing: Vec<String> = pushed.iter().filter(|s| !registered.contains(s)).cloned().collect();
+    let mut unfed: Vec<String> = registered.iter().filter(|s| !pushed.contains(s)).cloned().collect();
+
+    missing.sort();
+    missing.dedup();
+    unfed.sort();
+    (missing, unfed)
+}
+
+/// The oracle traps with "only managers can modify symbols" when the caller
+/// is not one of its managers; that case gets instructions to fix it.
+fn describe_rejection(target: &OracleTarget, feeder: Principal, (code, message): (RejectionCode, String)) -> String {
+    if message.contains("only managers") {
+        format!(
+            "{}: the feeder {} is not a manager of oracle {}; add it with the oracle's set_managers",
+            target.name, feeder, target.canister_id
+        )
+    } else {
+        format!("{}: oracle call failed: {:?} {}", target.name, code, message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symbols(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_diff_reports_both_directions() {
+        let (missing, unfed) = diff(&symbols(&["SOL", "BTC", "SOL", "AAPL"]), &symbols(&["BTC", "ETH"]));
+
+        assert_eq!(missing, symbols(&["AAPL", "SOL"]));
+        assert_eq!(unfed, symbols(&["ETH"]));
+    }
+
+    #[test]
+    fn test_missing_manager_rights_are_explained() {
+        let target = OracleTarget {
+            name: "production".to_string(),
+            canister_id: Principal::from_text("ryjl3-tyaaa-aaaaa-aaaba-cai").unwrap(),
+            enabled: true,
+            symbols: None,
+        };
+        let feeder = Principal::from_text("rrkah-fqaaa-aaaaa-aaaaq-cai").unwrap();
+
+        let denied = describe_rejection(
+            &target,
+            feeder,
+            (RejectionCode::CanisterError, "Unauthorized: only managers can modify symbols".to_string()),
+        );
+        assert_eq!(
+            denied,
+            "production: the feeder rrkah-fqaaa-aaaaa-aaaaq-cai is not a manager of oracle \
+             ryjl3-tyaaa-aaaaa-aaaba-cai; add it with the oracle's set_managers"
+        );
+
+        let other = describe_rejection(&target, feeder, (RejectionCode::DestinationInvalid, "no such canister".to_string()));
+        assert_eq!(other, "production: oracle call failed: DestinationInvalid no such canister");
+    }
+}
@@ -0,0 +1,113 @@
+use ic_cdk_timers::TimerId;
+use std::cell::{Cell, RefCell};
+use std::time::Duration;
+
+use crate::state::{get_timer_intent, set_timer_intent, update_metrics};
+
+thread_local! {
+    static TIMER: RefCell<Option<TimerId>> = const { RefCell::new(None) };
+    static ROUND_IN_PROGRESS: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Records that the timer should run and arms it.
+pub fn start(interval_secs: u64) {
+    set_timer_intent(true);
+    arm(interval_secs);
+}
+
+/// Records that the timer should stay stopped and disarms it.
+pub fn stop() {
+    set_timer_intent(false);
+    disarm();
+}
+
+/// Re-arms a running timer so a new interval takes effect immediately.
+pub fn reschedule(interval_secs: u64) {
+    if is_running() {
+        disarm();
+        arm(interval_secs);
+    }
+}
+
+/// Brings the timer back to the state operators last asked for.
+///
+/// Timers do not survive upgrades, so this runs from `post_upgrade`.
+/// `legacy_running` decides for canisters upgraded from before the intent
+/// was persisted.
+pub fn restore(interval_secs: u64, legacy_running: bool) {
+    let running = get_timer_intent().unwrap_or(legacy_running);
+    set_timer_intent(running);
+
+    if running {
+        arm(interval_secs);
+    } else {
+        update_metrics(|m| m.timer_running = false);
+    }
+}
+
+pub fn is_running() -> bool {
+    TIMER.with(|t| t.borrow().is_some())
+}
+
+fn arm(interval_secs: u64) {
+    if is_running() {
+        return;
+    }
+
+    let timer_id = ic_cdk_timers::set_timer_interval(Duration::from_secs(interval_secs), || {
+        ic_cdk::spawn(async {
+            if let Err(e) = crate::fetch_and_push_prices().await {
+                ic_cdk::println!("⏭ Scheduled round skipped: {}", e);
+            }
+        });
+    });
+
+    TIMER.with(|t| *t.borrow_mut() = Some(timer_id));
+    update_metrics(|m| m.timer_running = true);
+}
+
+fn disarm() {
+    if let Some(timer_id) = TIMER.with(|t| t.borrow_mut().take()) {
+        ic_cdk_timers::clear_timer(timer_id);
+    }
+    update_metrics(|m| m.timer_running = false);
+}
+
+/// Held for the duration of a fetch round so rounds never overlap.
+///
+/// The flag is cleared on drop, which also happens when a trap aborts the
+/// round after an await, since the CDK drops the pending future on cleanup.
+pub struct RoundGuard(());
+
+impl RoundGuard {
+    pub fn acquire() -> Option<Self> {
+        ROUND_IN_PROGRESS.with(|busy| {
+            if busy.get() {
+                None
+            } else {
+                busy.set(true);
+                Some(RoundGuard(()))
+            }
+        })
+    }
+}
+
+impl Drop for RoundGuard {
+    fn drop(&mut self) {
+        ROUND_IN_PROGRESS.with(|busy| busy.set(false));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_guard_prevents_overlap() {
+        let guard = RoundGuard::acquire().expect("first round should start");
+        assert!(RoundGuard::acquire().is_none());
+
+        drop(guard);
+        assert!(RoundGuard::acquire().is_some());
+    }
+}
@@ -0,0 +1,472 @@
+use candid::{CandidType, Principal};
+use serde::{Deserialize, Serialize};
+
+use crate::budget::Meter;
+use crate::types::SolanaRpcSettings;
+
+/// Solana rejects `getMultipleAccounts` requests with more than 100 public keys.
+pub const MAX_ACCOUNTS_PER_REQUEST: usize = 100;
+
+/// Cycles attached to each request; unused cycles are refunded.
+pub const CYCLES_PER_REQUEST: u128 = 3_000_000_000;
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum RpcSources {
+    Custom(Vec<RpcSource>),
+    Default(SolanaCluster),
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum SolanaCluster {
+    Mainnet,
+    Devnet,
+    Testnet,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum RpcSource {
+    Supported(SupportedRpcProviderId),
+    Custom(RpcEndpoint),
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum SupportedRpcProviderId {
+    AlchemyMainnet,
+    AlchemyDevnet,
+    AnkrMainnet,
+    AnkrDevnet,
+    DrpcMainnet,
+    DrpcDevnet,
+    HeliusMainnet,
+    HeliusDevnet,
+    PublicNodeMainnet,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RpcEndpoint {
+    pub url: String,
+    pub headers: Option<Vec<HttpHeader>>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HttpHeader {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum CommitmentLevel {
+    #[serde(rename = "processed")]
+    Processed,
+    #[serde(rename = "confirmed")]
+    Confirmed,
+    #[serde(rename = "finalized")]
+    Finalized,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum ConsensusStrategy {
+    Equality,
+    Threshold { min: u8, total: Option<u8> },
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct RpcConfig {
+    #[serde(rename = "responseSizeEstimate")]
+    pub response_size_estimate: Option<u64>,
+    #[serde(rename = "responseConsensus")]
+    pub response_consensus: Option<ConsensusStrategy>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub enum GetAccountInfoEncoding {
+    #[serde(rename = "base58")]
+    Base58,
+    #[serde(rename = "base64")]
+    Base64,
+    #[serde(rename = "base64+zstd")]
+    Base64Zstd,
+    #[serde(rename = "jsonParsed")]
+    JsonParsed,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct GetMultipleAccountsParams {
+    pub pubkeys: Vec<String>,
+    pub commitment: Option<CommitmentLevel>,
+    pub encoding: Option<GetAccountInfoEncoding>,
+    #[serde(rename = "dataSlice")]
+    pub data_slice: Option<()>,
+    #[serde(rename = "minContextSlot")]
+    pub min_context_slot: Option<u64>,
+}
+
+/// Slots advance every ~400ms, so providers only agree on one after rounding.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct GetSlotRpcConfig {
+    #[serde(rename = "responseSizeEstimate")]
+    pub response_size_estimate: Option<u64>,
+    #[serde(rename = "responseConsensus")]
+    pub response_consensus: Option<ConsensusStrategy>,
+    #[serde(rename = "roundingError")]
+    pub rounding_error: Option<u64>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct GetSlotParams {
+    pub commitment: Option<CommitmentLevel>,
+    #[serde(rename = "minContextSlot")]
+    pub min_context_slot: Option<u64>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub enum MultiGetSlotResult {
+    Consistent(GetSlotResult),
+    Inconsistent(Vec<(RpcSource, GetSlotResult)>),
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub enum GetSlotResult {
+    Ok(u64),
+    Err(RpcError),
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub enum MultiGetMultipleAccountsResult {
+    Consistent(GetMultipleAccountsResult),
+    Inconsistent(Vec<(RpcSource, GetMultipleAccountsResult)>),
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub enum GetMultipleAccountsResult {
+    Ok(Vec<Option<AccountInfo>>),
+    Err(RpcError),
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub enum RpcError {
+    ProviderError(candid::Reserved),
+    HttpOutcallError(candid::Reserved),
+    JsonRpcError(JsonRpcError),
+    ValidationError(String),
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct JsonRpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct AccountInfo {
+    pub lamports: u64,
+    pub data: AccountData,
+    pub owner: String,
+    pub executable: bool,
+    #[serde(rename = "rentEpoch")]
+    pub rent_epoch: u64,
+    pub space: u64,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub enum AccountData {
+    #[serde(rename = "legacyBinary")]
+    LegacyBinary(String),
+    #[serde(rename = "binary")]
+    Binary((String, AccountEncoding)),
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub enum AccountEncoding {
+    #[serde(rename = "binary")]
+    Binary,
+    #[serde(rename = "base58")]
+    Base58,
+    #[serde(rename = "base64")]
+    Base64,
+    #[serde(rename = "base64+zstd")]
+    Base64Zstd,
+    #[serde(rename = "jsonParsed")]
+    JsonParsed,
+}
+
+impl AccountData {
+    pub fn decode(&self) -> Result<Vec<u8>, String> {
+        use base64::Engine;
+
+        let base64_data = match self {
+            AccountData::Binary((data, _)) => data,
+            AccountData::LegacyBinary(data) => data,
+        };
+
+        base64::engine::general_purpose::STANDARD
+            .decode(base64_data)
+            .map_err(|e| format!("Failed to decode base64: {}", e))
+    }
+}
+
+impl std::fmt::Display for RpcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RpcError::ProviderError(_) => write!(f, "provider error"),
+            RpcError::HttpOutcallError(_) => write!(f, "HTTP outcall error"),
+            RpcError::JsonRpcError(e) => write!(f, "JSON-RPC error {}: {}", e.code, e.message),
+            RpcError::ValidationError(e) => write!(f, "validation error: {}", e),
+        }
+    }
+}
+
+/// Reads `pubkeys` in a single `getMultipleAccounts` call.
+///
+/// The outer error means the whole request failed; otherwise there is one
+/// entry per requested key, in request order, holding the raw account bytes
+/// or the reason that particular account could not be read.
+pub async fn get_multiple_accounts(
+    sol_rpc_canister_id: Principal,
+    settings: &SolanaRpcSettings,
+    pubkeys: &[String],
+    meter: &Meter,
+) -> Result<Vec<Result<Vec<u8>, String>>, String> {
+    let params = GetMultipleAccountsParams {
+        pubkeys: pubkeys.to_vec(),
+        commitment: settings.commitment.clone(),
+        encoding: Some(GetAccountInfoEncoding::Base64),
+        data_slice: None,
+        min_context_slot: None,
+    };
+
+    let rpc_config = settings.consensus.clone().map(|consensus| RpcConfig {
+        response_size_estimate: None,
+        response_consensus: Some(consensus),
+    });
+
+    let call_result: Result<(MultiGetMultipleAccountsResult,), _> = ic_cdk::api::call::call_with_payment128(
+        sol_rpc_canister_id,
+        "getMultipleAccounts",
+        (settings.sources.clone(), rpc_config, params),
+        CYCLES_PER_REQUEST,
+    ).await;
+    meter.record_call(CYCLES_PER_REQUEST);
+
+    match call_result {
+        Ok((MultiGetMultipleAccountsResult::Consistent(result),)) => {
+            let accounts = into_accounts(result, pubkeys.len())?;
+            Ok(accounts
+                .into_iter()
+                .map(|account| match account {
+                    Some(account_info) => account_info.data.decode(),
+                    None => Err("Account not found".to_string()),
+                })
+                .collect())
+        }
+        Ok((MultiGetMultipleAccountsResult::Inconsistent(results),)) => {
+            Ok(resolve_by_majority(results, pubkeys.len()))
+        }
+        Err(e) => Err(format!("RPC call failed: {:?}", e)),
+    }
+}
+
+/// Reads the current slot, rounded down to a multiple of 20 by the SOL RPC
+/// canister (its default) so that providers agree.
+///
+/// Rounding makes the slot up to 19 slots (~8s) older than it is, so slot
+/// ages computed from it can only be understated by that much.
+pub async fn get_slot(sol_rpc_canister_id: Principal, settings: &SolanaRpcSettings, meter: &Meter) -> Result<u64, String> {
+    let rpc_config = settings.consensus.clone().map(|consensus| GetSlotRpcConfig {
+        response_size_estimate: None,
+        response_consensus: Some(consensus),
+        rounding_error: None,
+    });
+    let params = GetSlotParams {
+        commitment: settings.commitment.clone(),
+        min_context_slot: None,
+    };
+
+    let call_result: Result<(MultiGetSlotResult,), _> = ic_cdk::api::call::call_with_payment128(
+        sol_rpc_canister_id,
+        "getSlot",
+        (settings.sources.clone(), rpc_config, Some(params)),
+        CYCLES_PER_REQUEST,
+    ).await;
+    meter.record_call(CYCLES_PER_REQUEST);
+
+    match call_result {
+        Ok((MultiGetSlotResult::Consistent(GetSlotResult::Ok(slot)),)) => Ok(slot),
+        Ok((MultiGetSlotResult::Consistent(GetSlotResult::Err(e)),)) => Err(format!("RPC returned error: {}", e)),
+        Ok((MultiGetSlotResult::Inconsistent(results),)) => median_slot(results),
+        Err(e) => Err(format!("RPC call failed: {:?}", e)),
+    }
+}
+
+/// Settles an `Inconsistent` slot: with more than half of the providers
+/// answering, their median is used, which no single provider can move far.
+fn median_slot(results: Vec<(RpcSource, GetSlotResult)>) -> Result<u64, String> {
+    let providers = results.len();
+    let mut slots: Vec<u64> = results
+        .into_iter()
+        .filter_map(|(_, result)| match result {
+            GetSlotResult::Ok(slot) => Some(slot),
+            GetSlotResult::Err(_) => None,
+        })
+        .collect();
+
+    if slots.len() * 2 <= providers {
+        return Err(format!("{} of {} RPC providers returned a slot", slots.len(), providers));
+    }
+
+    slots.sort_unstable();
+    Ok(slots[(slots.len() - 1) / 2])
+}
+
+fn into_accounts(
+    result: GetMultipleAccountsResult,
+    expected: usize,
+) -> Result<Vec<Option<AccountInfo>>, String> {
+    let accounts = match result {
+        GetMultipleAccountsResult::Ok(accounts) => accounts,
+        GetMultipleAccountsResult::Err(e) => return Err(format!("RPC returned error: {}", e)),
+    };
+
+    if accounts.len() != expected {
+        return Err(format!(
+            "RPC returned {} accounts for {} requested keys",
+            accounts.len(),
+            expected
+        ));
+    }
+
+    Ok(accounts)
+}
+
+/// Settles an `Inconsistent` response account by account.
+///
+/// Each provider votes with the bytes it returned for a given key (or with
+/// "not found"); providers that errored abstain. A value is accepted only if
+/// more than half of all queried providers returned it, so a single faulty
+/// provider can neither block nor forge a price.
+fn resolve_by_majority(
+    results: Vec<(RpcSource, GetMultipleAccountsResult)>,
+    expected: usize,
+) -> Vec<Result<Vec<u8>, String>> {
+    let providers = results.len();
+    let ballots: Vec<Vec<Option<Vec<u8>>>> = results
+        .into_iter()
+        .filter_map(|(_, result)| into_accounts(result, expected).ok())
+        .map(|accounts| {
+            accounts
+                .into_iter()
+                .map(|account| account.and_then(|info| info.data.decode().ok()))
+                .collect()
+        })
+        .collect();
+
+    (0..expected)
+        .map(|index| {
+            let mut tally: Vec<(&Option<Vec<u8>>, usize)> = Vec::new();
+            for ballot in &ballots {
+                let vote = &ballot[index];
+                match tally.iter_mut().find(|(value, _)| *value == vote) {
+                    Some((_, count)) => *count += 1,
+                    None => tally.push((vote, 1)),
+                }
+            }
+
+            match tally.into_iter().find(|(_, count)| *count * 2 > providers) {
+                Some((Some(bytes), _)) => Ok(bytes.clone()),
+                Some((None, _)) => Err("Account not found".to_string()),
+                None => Err(format!(
+                    "RPC providers disagree ({} responses, no majority)",
+                    providers
+                )),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(data: &[u8]) -> Option<AccountInfo> {
+        use base64::Engine;
+
+        Some(AccountInfo {
+            lamports: 1,
+            data: AccountData::Binary((
+                base64::engine::general_purpose::STANDARD.encode(data),
+                AccountEncoding::Base64,
+            )),
+            owner: String::new(),
+            executable: false,
+            rent_epoch: 0,
+            space: data.len() as u64,
+        })
+    }
+
+    fn provider(id: SupportedRpcProviderId, accounts: Vec<Option<AccountInfo>>) -> (RpcSource, GetMultipleAccountsResult) {
+        (RpcSource::Supported(id), GetMultipleAccountsResult::Ok(accounts))
+    }
+
+    #[test]
+    fn test_majority_resolves_each_account_independently() {
+        let results = vec![
+            provider(SupportedRpcProviderId::AlchemyMainnet, vec![account(&[1]), account(&[7])]),
+            provider(SupportedRpcProviderId::AnkrMainnet, vec![account(&[1]), account(&[8])]),
+            provider(SupportedRpcProviderId::HeliusMainnet, vec![account(&[2]), account(&[9])]),
+        ];
+
+        let resolved = resolve_by_majority(results, 2);
+
+        assert_eq!(resolved[0], Ok(vec![1]));
+        assert!(resolved[1].is_err());
+    }
+
+    #[test]
+    fn test_erroring_providers_abstain_but_count_towards_quorum() {
+        let results = vec![
+            provider(SupportedRpcProviderId::AlchemyMainnet, vec![account(&[1])]),
+            (
+                RpcSource::Supported(SupportedRpcProviderId::AnkrMainnet),
+                GetMultipleAccountsResult::Err(RpcError::ValidationError("boom".to_string())),
+            ),
+            (
+                RpcSource::Supported(SupportedRpcProviderId::HeliusMainnet),
+                GetMultipleAccountsResult::Err(RpcError::ValidationError("boom".to_string())),
+            ),
+        ];
+
+        assert!(resolve_by_majority(results, 1)[0].is_err());
+    }
+
+    #[test]
+    fn test_majority_can_agree_an_account_is_missing() {
+        let results = vec![
+            provider(SupportedRpcProviderId::AlchemyMainnet, vec![None]),
+            provider(SupportedRpcProviderId::AnkrMainnet, vec![None]),
+            provider(SupportedRpcProviderId::HeliusMainnet, vec![account(&[1])]),
+        ];
+
+        assert_eq!(resolve_by_majority(results, 1)[0], Err("Account not found".to_string()));
+    }
+
+    #[test]
+    fn test_median_slot_needs_a_majority() {
+        let slot = |id, result| (RpcSource::Supported(id), result);
+        let failed = || GetSlotResult::Err(RpcError::ValidationError("boom".to_string()));
+
+        let results = vec![
+            slot(SupportedRpcProviderId::AlchemyMainnet, GetSlotResult::Ok(300)),
+            slot(SupportedRpcProviderId::AnkrMainnet, GetSlotResult::Ok(1)),
+            slot(SupportedRpcProviderId::HeliusMainnet, GetSlotResult::Ok(320)),
+        ];
+        assert_eq!(median_slot(results), Ok(300));
+
+        let results = vec![
+            slot(SupportedRpcProviderId::AlchemyMainnet, GetSlotResult::Ok(300)),
+            slot(SupportedRpcProviderId::AnkrMainnet, failed()),
+            slot(SupportedRpcProviderId::HeliusMainnet, failed()),
+        ];
+        assert!(median_slot(results).is_err());
+    }
+}
@@ -0,0 +1,315 @@
+use candid::Principal;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+
+use crate::budget::Meter;
+use crate::pyth::{self, ParsedPrice, PriceStatus, PublisherStats};
+use crate::chainlink::ChainlinkSource;
+use crate::exchange::ExchangeSource;
+use crate::switchboard::SwitchboardSource;
+use crate::types::{FeedPriority, FeedSource, FeederConfig, HermesSettings, PythFeed, SolanaRpcSettings};
+use crate::{hermes, sol_rpc};
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + 'a>>;
+
+/// Quotes for one feed. Most sources give one; exchange feeds give one per
+/// ticker that answered.
+pub type FeedQuotes = Result<Vec<Quote>, String>;
+
+/// One entry per requested feed, in request order. The outer error means the
+/// whole request failed.
+pub type BatchResult = Result<Vec<FeedQuotes>, String>;
+
+/// A price as read from a source, before gating and scaling: the real value
+/// is `value * 10^exponent`.
+///
+/// Fields a source cannot report are `None` and the gating rules that need
+/// them are skipped. Sources without a notion of market status report
+/// `Trading`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quote {
+    pub value: i64,
+    pub exponent: i32,
+    pub conf: u64,
+    pub publish_time: i64,
+    /// Tag passed to the oracle as the price's `source`.
+    pub source: String,
+    pub status: PriceStatus,
+    pub pub_slot: Option<u64>,
+    pub curr_slot: Option<u64>,
+    pub num_publishers: Option<u32>,
+    /// Reported by legacy Pyth accounts, which list their publishers.
+    pub publishers: Option<PublisherStats>,
+    /// Traded volume in whole units of the base asset.
+    pub volume: Option<u64>,
+}
+
+impl Quote {
+    pub fn from_pyth(price: ParsedPrice, source: &str) -> Self {
+        Self {
+            value: price.price,
+            exponent: price.exponent,
+            conf: price.conf,
+            publish_time: price.publish_time,
+            source: source.to_string(),
+            status: price.status,
+            pub_slot: Some(price.pub_slot),
+            curr_slot: price.curr_slot,
+            num_publishers: price.num_publishers,
+            publishers: price.publishers,
+            volume: None,
+        }
+    }
+}
+
+/// A way of fetching prices, shared by every feed configured to use it.
+///
+/// Adapters read their per-feed parameters from the feeds they are given
+/// and their shared settings from `FeederConfig`, so adding a source means
+/// adding an adapter and a case to `adapter_for`; the round loop only sees
+/// this trait.
+pub trait PriceSource {
+    /// Identifies the adapter; feeds with the same name are fetched together.
+    fn name(&self) -> &'static str;
+
+    /// Most feeds a single `fetch` call may be given.
+    fn max_batch(&self) -> usize;
+
+    /// Cycles `fetch` attaches for `feeds`, which bounds what it can spend.
+    fn max_cycles(&self, feeds: &[PythFeed]) -> u128;
+
+    fn fetch<'a>(&'a self, feeds: &'a [PythFeed], meter: &'a Meter) -> BoxFuture<'a, BatchResult>;
+}
+
+/// Picks the adapter a feed's `source` asks for.
+pub fn adapter_for(feed: &PythFeed, config: &FeederConfig) -> Box<dyn PriceSource> {
+    match &feed.source {
+        Some(FeedSource::Hermes { .. }) => Box::new(HermesSource {
+            settings: config.hermes.clone().unwrap_or_default(),
+        }),
+        Some(FeedSource::Chainlink { .. }) => Box::new(ChainlinkSource {
+            settings: config.evm_rpc.clone().unwrap_or_default(),
+        }),
+        Some(FeedSource::Exchange { .. }) => Box::new(ExchangeSource {
+            endpoints: config.exchanges.clone().unwrap_or_default(),
+        }),
+        Some(FeedSource::SwitchboardAccount) => Box::new(SwitchboardSource {
+            sol_rpc_canister_id: config.sol_rpc_canister_id,
+            settings: config.solana_rpc.clone().unwrap_or_default(),
+        }),
+        Some(FeedSource::SolanaAccount) | None => Box::new(PythSolanaSource {
+            sol_rpc_canister_id: config.sol_rpc_canister_id,
+            settings: config.solana_rpc.clone().unwrap_or_default(),
+        }),
+    }
+}
+
+/// Groups feeds by adapter, keeping the order feeds were first seen in.
+pub fn group_by_adapter(
+    feeds: Vec<PythFeed>,
+    config: &FeederConfig,
+) -> Vec<(Box<dyn PriceSource>, Vec<PythFeed>)> {
+    let mut groups: Vec<(Box<dyn PriceSource>, Vec<PythFeed>)> = Vec::new();
+
+    for feed in feeds {
+        let adapter = adapter_for(&feed, config);
+        match groups.iter_mut().find(|(existing, _)| existing.name() == adapter.name()) {
+            Some((_, group)) => group.push(feed),
+            None => groups.push((adapter, vec![feed])),
+        }
+    }
+
+    groups
+}
+
+/// Feeds fetched together in one `fetch` call.
+pub struct Batch {
+    pub adapter: Rc<dyn PriceSource>,
+    pub feeds: Vec<PythFeed>,
+    pub max_cycles: u128,
+}
+
+/// Cuts feeds into batches, highest priority first. Within a priority, feeds
+/// are grouped by adapter and split to the adapter's `max_batch`.
+pub fn plan_batches(feeds: Vec<PythFeed>, config: &FeederConfig) -> Vec<Batch> {
+    let mut batches = Vec::new();
+
+    for priority in [FeedPriority::High, FeedPriority::Normal, FeedPriority::Low] {
+        let feeds: Vec<PythFeed> = feeds
+            .iter()
+            .filter(|feed| feed.priority.unwrap_or_default() == priority)
+            .cloned()
+            .collect();
+
+        for (adapter, feeds) in group_by_adapter(feeds, config) {
+            let adapter: Rc<dyn PriceSource> = Rc::from(adapter);
+            for chunk in feeds.chunks(adapter.max_batch().max(1)) {
+                batches.push(Batch {
+                    adapter: adapter.clone(),
+                    feeds: chunk.to_vec(),
+                    max_cycles: adapter.max_cycles(chunk),
+                });
+            }
+        }
+    }
+
+    batches
+}
+
+/// Pyth price accounts (legacy or `PriceUpdateV2`) read through the SOL RPC
+/// canister. Each feed's `account` is the account to read.
+pub struct PythSolanaSource {
+    pub sol_rpc_canister_id: Principal,
+    pub settings: SolanaRpcSettings,
+}
+
+impl PriceSource for PythSolanaSource {
+    fn name(&self) -> &'static str {
+        "pyth-solana"
+    }
+
+    fn max_batch(&self) -> usize {
+        sol_rpc::MAX_ACCOUNTS_PER_REQUEST
+    }
+
+    fn max_cycles(&self, _feeds: &[PythFeed]) -> u128 {
+        sol_rpc::CYCLES_PER_REQUEST
+    }
+
+    fn fetch<'a>(&'a self, feeds: &'a [PythFeed], meter: &'a Meter) -> BoxFuture<'a, BatchResult> {
+        Box::pin(async move {
+            let pubkeys: Vec<String> = feeds.iter().map(|feed| feed.account.clone()).collect();
+            let accounts = sol_rpc::get_multiple_accounts(self.sol_rpc_canister_id, &self.settings, &pubkeys, meter).await?;
+
+            Ok(accounts
+                .into_iter()
+                .map(|account| {
+                    let price = pyth::decode_account(&account?)?;
+                    Ok(vec![Quote::from_pyth(price, "pyth")])
+                })
+                .collect())
+        })
+    }
+}
+
+/// Pyth prices from Hermes over HTTPS outcalls. Each feed's source names its
+/// Pyth feed id.
+pub struct HermesSource {
+    pub settings: HermesSettings,
+}
+
+impl PriceSource for HermesSource {
+    fn name(&self) -> &'static str {
+        "pyth-hermes"
+    }
+
+    fn max_batch(&self) -> usize {
+        hermes::MAX_IDS_PER_REQUEST
+    }
+
+    fn max_cycles(&self, feeds: &[PythFeed]) -> u128 {
+        hermes::request_cycles(&self.settings, &feed_ids(feeds))
+    }
+
+    fn fetch<'a>(&'a self, feeds: &'a [PythFeed], meter: &'a Meter) -> BoxFuture<'a, BatchResult> {
+        Box::pin(async move {
+            let prices = hermes::get_prices_at(&self.settings, &feed_ids(feeds), hermes::publish_time(ic_cdk::api::time()), meter).await?;
+
+            Ok(prices
+                .into_iter()
+                .map(|price| price.map(|price| vec![Quote::from_pyth(price, "pyth-hermes")]))
+                .collect())
+        })
+    }
+}
+
+fn feed_ids(feeds: &[PythFeed]) -> Vec<String> {
+    feeds
+        .iter()
+        .map(|feed| feed.hermes_feed_id().unwrap_or_default().to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(symbol: &str, source: Option<FeedSource>) -> PythFeed {
+        PythFeed {
+            symbol: symbol.to_string(),
+            account: String::new(),
+            enabled: true,
+            push_rules: None,
+            quarantine: None,
+            gating: None,
+            source,
+            priority: None,
+            transform: None,
+        }
+    }
+
+    fn config() -> FeederConfig {
+        FeederConfig {
+            oracle_canister_id: Principal::anonymous(),
+            sol_rpc_canister_id: Principal::anonymous(),
+            update_interval_secs: 10,
+            solana_rpc: None,
+            quarantine: None,
+            hermes: None,
+            evm_rpc: None,
+            exchanges: None,
+            aggregation: None,
+            fetch: None,
+            oracle_targets: None,
+            register_symbols: None,
+        }
+    }
+
+    #[test]
+    fn test_feeds_are_grouped_by_adapter_in_order() {
+        let config = config();
+        let hermes = || Some(FeedSource::Hermes { feed_id: "ab".to_string() });
+
+        let groups = group_by_adapter(
+            vec![
+                feed("BTC", None),
+                feed("SOL", hermes()),
+                feed("ETH", Some(FeedSource::SolanaAccount)),
+                feed("AAPL", hermes()),
+            ],
+            &config,
+        );
+
+        let summary: Vec<(&str, Vec<&str>)> = groups
+            .iter()
+            .map(|(adapter, feeds)| (adapter.name(), feeds.iter().map(|f| f.symbol.as_str()).collect()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![("pyth-solana", vec!["BTC", "ETH"]), ("pyth-hermes", vec!["SOL", "AAPL"])]
+        );
+    }
+
+    #[test]
+    fn test_batches_put_higher_priorities_first() {
+        let with_priority = |symbol, priority| PythFeed { priority, ..feed(symbol, None) };
+
+        let batches = plan_batches(
+            vec![
+                with_priority("DOGE", Some(FeedPriority::Low)),
+                with_priority("ETH", None),
+                with_priority("BTC", Some(FeedPriority::High)),
+                with_priority("SOL", Some(FeedPriority::Normal)),
+            ],
+            &config(),
+        );
+
+        let summary: Vec<Vec<&str>> = batches
+            .iter()
+            .map(|batch| batch.feeds.iter().map(|f| f.symbol.as_str()).collect())
+            .collect();
+        assert_eq!(summary, vec![vec!["BTC"], vec!["ETH", "SOL"], vec!["DOGE"]]);
+        assert!(batches.iter().all(|batch| batch.max_cycles == sol_rpc::CYCLES_PER_REQUEST));
+    }
+}
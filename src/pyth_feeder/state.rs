@@ -2,7 +2,11 @@ use candid::Principal;
 use ic_stable_structures::memory_manager::{MemoryId, MemoryManager, VirtualMemory};
 use ic_stable_structures::{DefaultMemoryImpl, StableBTreeMap, StableCell};
 use std::cell::RefCell;
-use crate::types::{PythFeed, FeederConfig, FeederMetrics};
+use crate::pyth::PublisherStats;
+use crate::types::{
+    FeedHealth, FeedUpdateResult, FeederConfig, FeederMetrics, LastPush, PythFeed, Role, RoundRecord, TargetDelivery,
+    TargetStatus, TargetSymbol, Versioned, SCHEMA_VERSION,
+};
 
 type Memory = VirtualMemory<DefaultMemoryImpl>;
 
@@ -10,19 +14,42 @@ const CONFIG_MEMORY_ID: MemoryId = MemoryId::new(0);
 const FEEDS_MEMORY_ID: MemoryId = MemoryId::new(1);
 const METRICS_MEMORY_ID: MemoryId = MemoryId::new(2);
 const MANAGER_MEMORY_ID: MemoryId = MemoryId::new(3);
+const LAST_PUSHED_MEMORY_ID: MemoryId = MemoryId::new(4);
+const ROLES_MEMORY_ID: MemoryId = MemoryId::new(5);
+const ROUNDS_MEMORY_ID: MemoryId = MemoryId::new(6);
+const FEED_HEALTH_MEMORY_ID: MemoryId = MemoryId::new(7);
+const TIMER_INTENT_MEMORY_ID: MemoryId = MemoryId::new(8);
+const TARGET_STATUS_MEMORY_ID: MemoryId = MemoryId::new(9);
+const TARGET_LAST_PUSHED_MEMORY_ID: MemoryId = MemoryId::new(10);
+
+const TIMER_INTENT_UNSET: u8 = 0;
+const TIMER_INTENT_STOPPED: u8 = 1;
+const TIMER_INTENT_RUNNING: u8 = 2;
+
+/// Number of fetch rounds kept in the round log.
+pub const ROUND_LOG_CAPACITY: u64 = 100;
 
 thread_local! {
     static MEMORY_MANAGER: RefCell<MemoryManager<DefaultMemoryImpl>> =
         RefCell::new(MemoryManager::init(DefaultMemoryImpl::default()));
 
-    static CONFIG: RefCell<StableCell<FeederConfig, Memory>> = RefCell::new(
+    static CONFIG: RefCell<StableCell<Versioned<FeederConfig>, Memory>> = RefCell::new(
         StableCell::init(
             MEMORY_MANAGER.with(|m| m.borrow().get(CONFIG_MEMORY_ID)),
-            FeederConfig {
+            Versioned::current(FeederConfig {
                 oracle_canister_id: Principal::anonymous(),
                 sol_rpc_canister_id: Principal::anonymous(),
                 update_interval_secs: 10,
-            }
+                solana_rpc: None,
+                quarantine: None,
+                hermes: None,
+                evm_rpc: None,
+                exchanges: None,
+                aggregation: None,
+                fetch: None,
+                oracle_targets: None,
+                register_symbols: None,
+            })
         ).expect("Failed to initialize CONFIG")
     );
 
@@ -32,38 +59,123 @@ thread_local! {
         )
     );
 
-    static METRICS: RefCell<StableCell<FeederMetrics, Memory>> = RefCell::new(
+    static METRICS: RefCell<StableCell<Versioned<FeederMetrics>, Memory>> = RefCell::new(
         StableCell::init(
             MEMORY_MANAGER.with(|m| m.borrow().get(METRICS_MEMORY_ID)),
-            FeederMetrics {
+            Versioned::current(FeederMetrics {
                 total_updates: 0,
                 successful_updates: 0,
                 failed_updates: 0,
                 last_update_time: 0,
                 active_feeds: 0,
                 timer_running: false,
-            }
+                cycles_spent: None,
+                cycles_spent_today: None,
+                cycles_day: None,
+                last_round_cycles: None,
+                skipped_for_budget: None,
+                cycles_spent_outside_rounds: None,
+            })
         ).expect("Failed to initialize METRICS")
     );
 
+    // Single manager from before roles existed; only read to migrate it.
     static MANAGER: RefCell<StableCell<Principal, Memory>> = RefCell::new(
         StableCell::init(
             MEMORY_MANAGER.with(|m| m.borrow().get(MANAGER_MEMORY_ID)),
             Principal::anonymous()
         ).expect("Failed to initialize MANAGER")
     );
+
+    static LAST_PUSHED: RefCell<StableBTreeMap<String, LastPush, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(LAST_PUSHED_MEMORY_ID))
+        )
+    );
+
+    static ROLES: RefCell<StableBTreeMap<String, Role, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(ROLES_MEMORY_ID))
+        )
+    );
+
+    static ROUNDS: RefCell<StableBTreeMap<u64, RoundRecord, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(ROUNDS_MEMORY_ID))
+        )
+    );
+
+    static FEED_HEALTH: RefCell<StableBTreeMap<String, FeedHealth, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(FEED_HEALTH_MEMORY_ID))
+        )
+    );
+
+    static TARGET_STATUS: RefCell<StableBTreeMap<String, TargetStatus, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(TARGET_STATUS_MEMORY_ID))
+        )
+    );
+
+    static TARGET_LAST_PUSHED: RefCell<StableBTreeMap<TargetSymbol, LastPush, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(TARGET_LAST_PUSHED_MEMORY_ID))
+        )
+    );
+
+    static TIMER_INTENT: RefCell<StableCell<u8, Memory>> = RefCell::new(
+        StableCell::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(TIMER_INTENT_MEMORY_ID)),
+            TIMER_INTENT_UNSET
+        ).expect("Failed to initialize TIMER_INTENT")
+    );
 }
 
 pub fn get_config() -> FeederConfig {
-    CONFIG.with(|c| c.borrow().get().clone())
+    CONFIG.with(|c| c.borrow().get().value.clone())
 }
 
 pub fn set_config(config: FeederConfig) {
     CONFIG.with(|c| {
-        c.borrow_mut().set(config).expect("Failed to set config");
+        c.borrow_mut().set(Versioned::current(config)).expect("Failed to set config");
     });
 }
 
+/// Brings stable data written by an older build up to `SCHEMA_VERSION` and
+/// returns the version it was at. Runs first in `post_upgrade`.
+///
+/// Each step moves data one version forward; steps run in order, so data
+/// any number of versions behind ends up current.
+pub fn migrate() -> u32 {
+    let from = CONFIG.with(|c| c.borrow().get().version);
+    if from > SCHEMA_VERSION {
+        ic_cdk::trap(&format!(
+            "Stable data is at schema version {}, newer than this build's {}",
+            from, SCHEMA_VERSION
+        ));
+    }
+
+    if from < 2 {
+        // Version 2 tracks last pushes per target. Every current target starts
+        // from the shared record, so the upgrade does not trigger a push.
+        let targets = get_config().targets();
+        let pushes: Vec<(String, LastPush)> = LAST_PUSHED.with(|l| l.borrow().iter().collect());
+        for (symbol, last_push) in pushes {
+            for target in targets.iter().filter(|t| t.accepts(&symbol)) {
+                set_target_last_push(&target.name, &symbol, last_push.clone());
+            }
+        }
+    }
+
+    if from < SCHEMA_VERSION {
+        // Rewriting the cells tags them with the current version.
+        set_config(get_config());
+        update_metrics(|_| {});
+    }
+
+    from
+}
+
 pub fn add_feed(feed: PythFeed) {
     FEEDS.with(|f| {
         f.borrow_mut().insert(feed.symbol.clone(), feed);
@@ -71,7 +183,23 @@ pub fn add_feed(feed: PythFeed) {
 }
 
 pub fn remove_feed(symbol: &str) -> Option<PythFeed> {
-    FEEDS.with(|f| f.borrow_mut().remove(&symbol.to_string()))
+    let feed = FEEDS.with(|f| f.borrow_mut().remove(&symbol.to_string()))?;
+    FEED_HEALTH.with(|h| h.borrow_mut().remove(&symbol.to_string()));
+
+    // Pushes are recorded under the oracle symbol, which another feed may
+    // still publish.
+    let oracle_symbol = feed.oracle_symbol().to_string();
+    if get_all_feeds().iter().any(|other| other.oracle_symbol() == oracle_symbol) {
+        return Some(feed);
+    }
+    LAST_PUSHED.with(|l| l.borrow_mut().remove(&oracle_symbol));
+    TARGET_LAST_PUSHED.with(|l| {
+        let keys: Vec<TargetSymbol> = l.borrow().iter().map(|(key, _)| key).filter(|key| key.symbol == oracle_symbol).collect();
+        for key in keys {
+            l.borrow_mut().remove(&key);
+        }
+    });
+    Some(feed)
 }
 
 pub fn get_feed(symbol: &str) -> Option<PythFeed> {
@@ -97,8 +225,130 @@ pub fn get_enabled_feeds() -> Vec<PythFeed> {
     })
 }
 
+pub fn get_last_push(symbol: &str) -> Option<LastPush> {
+    LAST_PUSHED.with(|l| l.borrow().get(&symbol.to_string()))
+}
+
+pub fn set_last_push(symbol: &str, last_push: LastPush) {
+    LAST_PUSHED.with(|l| {
+        l.borrow_mut().insert(symbol.to_string(), last_push);
+    });
+}
+
+pub fn get_target_last_push(target: &str, symbol: &str) -> Option<LastPush> {
+    TARGET_LAST_PUSHED.with(|l| {
+        l.borrow().get(&TargetSymbol { target: target.to_string(), symbol: symbol.to_string() })
+    })
+}
+
+pub fn set_target_last_push(target: &str, symbol: &str, last_push: LastPush) {
+    TARGET_LAST_PUSHED.with(|l| {
+        let key = TargetSymbol { target: target.to_string(), symbol: symbol.to_string() };
+        l.borrow_mut().insert(key, last_push);
+    });
+}
+
+pub fn next_round_id() -> u64 {
+    ROUNDS.with(|r| r.borrow().last_key_value().map_or(0, |(id, _)| id + 1))
+}
+
+/// Appends a round, dropping the oldest ones beyond `ROUND_LOG_CAPACITY`.
+pub fn append_round(round: RoundRecord) {
+    ROUNDS.with(|r| {
+        let mut rounds = r.borrow_mut();
+        rounds.insert(round.id, round);
+
+        while rounds.len() > ROUND_LOG_CAPACITY {
+            match rounds.first_key_value() {
+                Some((oldest, _)) => rounds.remove(&oldest),
+                None => break,
+            };
+        }
+    });
+}
+
+/// Returns up to `n` rounds, most recent first.
+pub fn get_recent_rounds(n: usize) -> Vec<RoundRecord> {
+    ROUNDS.with(|r| {
+        r.borrow()
+            .iter()
+            .rev()
+            .take(n)
+            .map(|(_, round)| round)
+            .collect()
+    })
+}
+
+pub fn get_feed_health(symbol: &str) -> Option<FeedHealth> {
+    FEED_HEALTH.with(|h| h.borrow().get(&symbol.to_string()))
+}
+
+pub fn record_feed_health(result: &FeedUpdateResult, now: u64) {
+    FEED_HEALTH.with(|h| {
+        let mut health = h.borrow().get(&result.symbol).unwrap_or_default();
+        health.record(result, now);
+        h.borrow_mut().insert(result.symbol.clone(), health);
+    });
+}
+
+pub fn record_publishers(symbol: &str, stats: PublisherStats) {
+    FEED_HEALTH.with(|h| {
+        let mut health = h.borrow().get(&symbol.to_string()).unwrap_or_default();
+        health.publishers = Some(stats);
+        h.borrow_mut().insert(symbol.to_string(), health);
+    });
+}
+
+/// The last publisher stats of each feed that reported them.
+pub fn get_all_publisher_stats() -> Vec<(String, PublisherStats)> {
+    FEED_HEALTH.with(|h| {
+        h.borrow()
+            .iter()
+            .filter_map(|(symbol, health)| Some((symbol, health.publishers?)))
+            .collect()
+    })
+}
+
+pub fn record_delivery(delivery: &TargetDelivery, now: u64) {
+    TARGET_STATUS.with(|t| {
+        let mut status = t.borrow().get(&delivery.target).unwrap_or_default();
+        status.record(delivery, now);
+        t.borrow_mut().insert(delivery.target.clone(), status);
+    });
+}
+
+pub fn get_target_statuses() -> Vec<(String, TargetStatus)> {
+    TARGET_STATUS.with(|t| t.borrow().iter().collect())
+}
+
+pub fn reset_failure_streak(symbol: &str) {
+    FEED_HEALTH.with(|h| {
+        let existing = h.borrow().get(&symbol.to_string());
+        if let Some(mut health) = existing {
+            health.consecutive_failures = 0;
+            h.borrow_mut().insert(symbol.to_string(), health);
+        }
+    });
+}
+
+/// Whether operators want the timer running, or `None` if never recorded.
+pub fn get_timer_intent() -> Option<bool> {
+    match TIMER_INTENT.with(|t| *t.borrow().get()) {
+        TIMER_INTENT_RUNNING => Some(true),
+        TIMER_INTENT_STOPPED => Some(false),
+        _ => None,
+    }
+}
+
+pub fn set_timer_intent(running: bool) {
+    let intent = if running { TIMER_INTENT_RUNNING } else { TIMER_INTENT_STOPPED };
+    TIMER_INTENT.with(|t| {
+        t.borrow_mut().set(intent).expect("Failed to set timer intent");
+    });
+}
+
 pub fn get_metrics() -> FeederMetrics {
-    METRICS.with(|m| m.borrow().get().clone())
+    METRICS.with(|m| m.borrow().get().value.clone())
 }
 
 pub fn update_metrics<F>(updater: F)
@@ -106,23 +356,122 @@ where
     F: FnOnce(&mut FeederMetrics),
 {
     METRICS.with(|m| {
-        let mut metrics = m.borrow().get().clone();
+        let mut metrics = m.borrow().get().value.clone();
         updater(&mut metrics);
-        m.borrow_mut().set(metrics).expect("Failed to update metrics");
+        m.borrow_mut().set(Versioned::current(metrics)).expect("Failed to update metrics");
     });
 }
 
-pub fn get_manager() -> Principal {
-    MANAGER.with(|m| m.borrow().get().clone())
+pub fn get_legacy_manager() -> Principal {
+    MANAGER.with(|m| *m.borrow().get())
+}
+
+pub fn get_role(principal: &Principal) -> Option<Role> {
+    ROLES.with(|r| r.borrow().get(&principal.to_text()))
 }
 
-pub fn set_manager(manager: Principal) {
-    MANAGER.with(|m| {
-        m.borrow_mut().set(manager).expect("Failed to set manager");
+pub fn set_role(principal: &Principal, role: Role) {
+    if *principal == Principal::anonymous() {
+        ic_cdk::trap("Cannot grant a role to the anonymous principal");
+    }
+    ROLES.with(|r| {
+        r.borrow_mut().insert(principal.to_text(), role);
     });
 }
 
-pub fn is_manager(principal: &Principal) -> bool {
-    let manager = get_manager();
-    manager == *principal
+pub fn remove_role(principal: &Principal) -> Option<Role> {
+    ROLES.with(|r| r.borrow_mut().remove(&principal.to_text()))
+}
+
+pub fn get_all_roles() -> Vec<(String, Role)> {
+    ROLES.with(|r| r.borrow().iter().collect())
+}
+
+pub fn roles_is_empty() -> bool {
+    ROLES.with(|r| r.borrow().is_empty())
+}
+
+pub fn count_admins() -> usize {
+    ROLES.with(|r| {
+        r.borrow()
+            .iter()
+            .filter(|(_, role)| *role == Role::Admin)
+            .count()
+    })
+}
+
+pub fn has_role(principal: &Principal, required: Role) -> bool {
+    get_role(principal).is_some_and(|role| role >= required)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{FeedTransform, TransformV1};
+
+    #[test]
+    fn test_round_log_keeps_only_recent_rounds() {
+        for _ in 0..ROUND_LOG_CAPACITY + 5 {
+            append_round(RoundRecord {
+                id: next_round_id(),
+                started_at: 0,
+                finished_at: 0,
+                results: vec![],
+                aggregates: None,
+                skipped: None,
+                deliveries: None,
+            });
+        }
+
+        let rounds = get_recent_rounds(usize::MAX);
+        assert_eq!(rounds.len() as u64, ROUND_LOG_CAPACITY);
+        assert_eq!(rounds[0].id, ROUND_LOG_CAPACITY + 4);
+        assert_eq!(rounds.last().unwrap().id, 5);
+    }
+
+    #[test]
+    fn test_removing_a_feed_forgets_its_pushes_to_every_target() {
+        add_feed(feed("BTC", None));
+        let push = LastPush { price: 100, timestamp: 1 };
+        set_last_push("BTC", push.clone());
+        for target in ["prod", "staging"] {
+            set_target_last_push(target, "BTC", push.clone());
+            set_target_last_push(target, "ETH", push.clone());
+        }
+
+        remove_feed("BTC");
+        assert!(get_last_push("BTC").is_none());
+        assert!(get_target_last_push("prod", "BTC").is_none() && get_target_last_push("staging", "BTC").is_none());
+        assert!(get_target_last_push("prod", "ETH").is_some() && get_target_last_push("staging", "ETH").is_some());
+    }
+
+    #[test]
+    fn test_removing_a_renamed_feed_forgets_pushes_once_no_feed_publishes_them() {
+        add_feed(feed("BTCUSD", Some("BTC/USD")));
+        add_feed(feed("BTCUSD_HERMES", Some("BTC/USD")));
+        let push = LastPush { price: 100, timestamp: 1 };
+        set_last_push("BTC/USD", push.clone());
+        set_target_last_push("prod", "BTC/USD", push);
+
+        remove_feed("BTCUSD");
+        assert!(get_last_push("BTC/USD").is_some() && get_target_last_push("prod", "BTC/USD").is_some());
+
+        remove_feed("BTCUSD_HERMES");
+        assert!(get_last_push("BTC/USD").is_none() && get_target_last_push("prod", "BTC/USD").is_none());
+    }
+
+    fn feed(symbol: &str, oracle_symbol: Option<&str>) -> PythFeed {
+        let oracle_symbol = oracle_symbol.map(str::to_string);
+        PythFeed {
+            symbol: symbol.to_string(),
+            account: String::new(),
+            enabled: true,
+            push_rules: None,
+            quarantine: None,
+            gating: None,
+            source: None,
+            priority: None,
+            transform: Some(FeedTransform::V1(TransformV1 { oracle_symbol, ..TransformV1::default() })),
+        }
+    }
 }
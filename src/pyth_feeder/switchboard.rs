@@ -0,0 +1,201 @@
+use candid::Principal;
+
+use crate::budget::Meter;
+use crate::fixed_point::{self, RoundingMode};
+use crate::pyth::PriceStatus;
+use crate::sol_rpc;
+use crate::source::{BatchResult, BoxFuture, PriceSource, Quote};
+use crate::types::{PythFeed, SolanaRpcSettings};
+
+/// Anchor discriminator of `PullFeedAccountData`.
+pub const PULL_FEED_DISCRIMINATOR: [u8; 8] = [196, 27, 108, 196, 10, 215, 219, 40];
+
+/// Switchboard results are fixed-point with 18 decimals.
+const RESULT_EXPONENT: i32 = -18;
+
+/// Offsets from the start of the account, discriminator included.
+const LAST_UPDATE_TIMESTAMP_OFFSET: usize = 2216;
+const RESULT_OFFSET: usize = 2264;
+/// `CurrentResult`: six `i128`s, two `u8`s and padding, then three slots.
+const RESULT_SIZE: usize = 128;
+
+/// The aggregated result of a Switchboard on-demand pull feed, still in
+/// Switchboard's units.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PullFeedResult {
+    pub value: i128,
+    pub std_dev: i128,
+    pub num_samples: u8,
+    pub slot: u64,
+    pub min_slot: u64,
+    pub max_slot: u64,
+    pub last_update_timestamp: i64,
+}
+
+/// Decodes the `result` of a `PullFeedAccountData` account.
+pub fn decode_pull_feed(data: &[u8]) -> Result<PullFeedResult, String> {
+    if data.len() < 8 || data[..8] != PULL_FEED_DISCRIMINATOR {
+        return Err("Not a Switchboard pull feed account".to_string());
+    }
+    if data.len() < RESULT_OFFSET + RESULT_SIZE {
+        return Err(format!(
+            "Switchboard account is {} bytes, expected at least {}",
+            data.len(),
+            RESULT_OFFSET + RESULT_SIZE
+        ));
+    }
+
+    let i128_at = |offset: usize| i128::from_le_bytes(data[offset..offset + 16].try_into().expect("16 bytes"));
+    let u64_at = |offset: usize| u64::from_le_bytes(data[offset..offset + 8].try_into().expect("8 bytes"));
+    let result = RESULT_OFFSET;
+
+    Ok(PullFeedResult {
+        value: i128_at(result),
+        std_dev: i128_at(result + 16),
+        num_samples: data[result + 96],
+        slot: u64_at(result + 104),
+        min_slot: u64_at(result + 112),
+        max_slot: u64_at(result + 120),
+        last_update_timestamp: u64_at(LAST_UPDATE_TIMESTAMP_OFFSET) as i64,
+    })
+}
+
+/// Turns a result into a quote. `curr_slot` is the chain's current slot, so
+/// the feed's `max_slot_age` gating rule applies to Switchboard feeds too.
+///
+/// An 18-decimal value overflows `i64` above about 9.2, so digits beyond
+/// what fits are truncated; at most 19 significant digits remain.
+pub fn to_quote(result: &PullFeedResult, curr_slot: u64) -> Result<Quote, String> {
+    if result.num_samples == 0 || result.slot == 0 {
+        return Err("Switchboard feed has no result".to_string());
+    }
+    if result.value <= 0 {
+        return Err(format!("Switchboard value is {}", result.value));
+    }
+
+    let mut value = result.value;
+    let mut exponent = RESULT_EXPONENT;
+    while value > i64::MAX as i128 {
+        value /= 10;
+        exponent += 1;
+    }
+    if exponent > 0 {
+        return Err(format!("Switchboard value {} is out of range", result.value));
+    }
+
+    let conf = fixed_point::rescale(result.std_dev.max(0), RESULT_EXPONENT, (-exponent) as u32, RoundingMode::Ceiling)
+        .ok()
+        .and_then(|conf| u64::try_from(conf).ok())
+        .ok_or_else(|| format!("Switchboard std deviation {} is out of range", result.std_dev))?;
+
+    Ok(Quote {
+        value: value as i64,
+        exponent,
+        conf,
+        publish_time: result.last_update_timestamp,
+        source: "switchboard".to_string(),
+        status: PriceStatus::Trading,
+        pub_slot: Some(result.slot),
+        curr_slot: Some(curr_slot),
+        num_publishers: Some(result.num_samples as u32),
+        publishers: None,
+        volume: None,
+    })
+}
+
+/// Switchboard on-demand pull feeds read through the SOL RPC canister, like
+/// Pyth price accounts. Each feed's `account` is the pull feed to read.
+pub struct SwitchboardSource {
+    pub sol_rpc_canister_id: Principal,
+    pub settings: SolanaRpcSettings,
+}
+
+impl PriceSource for SwitchboardSource {
+    fn name(&self) -> &'static str {
+        "switchboard"
+    }
+
+    fn max_batch(&self) -> usize {
+        sol_rpc::MAX_ACCOUNTS_PER_REQUEST
+    }
+
+    /// `getSlot` and `getMultipleAccounts`.
+    fn max_cycles(&self, _feeds: &[PythFeed]) -> u128 {
+        2 * sol_rpc::CYCLES_PER_REQUEST
+    }
+
+    fn fetch<'a>(&'a self, feeds: &'a [PythFeed], meter: &'a Meter) -> BoxFuture<'a, BatchResult> {
+        Box::pin(async move {
+            let curr_slot = sol_rpc::get_slot(self.sol_rpc_canister_id, &self.settings, meter)
+                .await
+                .map_err(|e| format!("Could not read the current slot: {}", e))?;
+
+            let pubkeys: Vec<String> = feeds.iter().map(|feed| feed.account.clone()).collect();
+            let accounts = sol_rpc::get_multiple_accounts(self.sol_rpc_canister_id, &self.settings, &pubkeys, meter).await?;
+
+            Ok(accounts
+                .into_iter()
+                .map(|account| {
+                    let result = decode_pull_feed(&account?)?;
+                    Ok(vec![to_quote(&result, curr_slot)?])
+                })
+                .collect())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> Vec<u8> {
+        use base64::Engine;
+
+        base64::engine::general_purpose::STANDARD
+            .decode(include_str!("fixtures/sol_usd_switchboard_pull_feed.b64").trim())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_fixture_pull_feed() {
+        let result = decode_pull_feed(&fixture()).unwrap();
+
+        assert_eq!(result.value, 142_350_000_000_000_000_000);
+        assert_eq!(result.std_dev, 51_200_000_000_000_000);
+        assert_eq!(result.num_samples, 5);
+        assert_eq!((result.slot, result.min_slot, result.max_slot), (289_345_125, 289_345_122, 289_345_125));
+        assert_eq!(result.last_update_timestamp, 1_728_000_002);
+
+        let quote = to_quote(&result, 289_345_140).unwrap();
+        assert_eq!((quote.value, quote.exponent, quote.conf), (1_423_500_000_000_000_000, -16, 512_000_000_000_000));
+        assert_eq!((quote.pub_slot, quote.curr_slot, quote.num_publishers), (Some(289_345_125), Some(289_345_140), Some(5)));
+    }
+
+    #[test]
+    fn test_rejects_other_accounts() {
+        let mut data = fixture();
+        assert!(decode_pull_feed(&data[..RESULT_OFFSET + RESULT_SIZE - 1]).is_err());
+
+        data[0] ^= 1;
+        assert_eq!(decode_pull_feed(&data), Err("Not a Switchboard pull feed account".to_string()));
+    }
+
+    #[test]
+    fn test_small_values_keep_all_18_decimals() {
+        let result = PullFeedResult {
+            value: 1_000_123_456_789_012_345,
+            std_dev: 1,
+            num_samples: 3,
+            slot: 10,
+            min_slot: 10,
+            max_slot: 10,
+            last_update_timestamp: 0,
+        };
+
+        let quote = to_quote(&result, 10).unwrap();
+        assert_eq!((quote.value, quote.exponent, quote.conf), (1_000_123_456_789_012_345, -18, 1));
+
+        assert!(to_quote(&PullFeedResult { num_samples: 0, ..result.clone() }, 10).is_err());
+        assert!(to_quote(&PullFeedResult { value: -1, ..result }, 10).is_err());
+    }
+}
@@ -0,0 +1,95 @@
+use crate::fixed_point::{self, RoundingMode, MAX_POW10};
+
+/// `1 / (value * 10^exponent)` with `decimals` decimal places, along with
+/// its confidence. The relative uncertainty carries over unchanged, rounded
+/// up.
+pub fn invert(value: i64, exponent: i32, conf: u64, decimals: u32, mode: RoundingMode) -> Result<(i64, u64), String> {
+    if value <= 0 {
+        return Err(format!("Cannot invert a price of {}", value));
+    }
+
+    let shift = decimals as i64 - exponent as i64;
+    if !(0..=MAX_POW10 as i64).contains(&shift) {
+        return Err(format!("Inverse of {}e{} does not fit in {} decimals", value, exponent, decimals));
+    }
+
+    let inverted = fixed_point::divide(10i128.pow(shift as u32), value as i128, mode);
+    let inverted = i64::try_from(inverted)
+        .map_err(|_| format!("Inverse of {}e{} does not fit in {} decimals", value, exponent, decimals))?;
+    if inverted == 0 {
+        return Err(format!("Inverse of {}e{} rounds to zero at {} decimals", value, exponent, decimals));
+    }
+
+    let conf = (inverted as i128)
+        .checked_mul(conf as i128)
+        .map(|product| fixed_point::divide(product, value as i128, RoundingMode::Ceiling))
+        .and_then(|conf| u64::try_from(conf).ok())
+        .ok_or_else(|| format!("Confidence of the inverse of {}e{} is out of range", value, exponent))?;
+
+    Ok((inverted, conf))
+}
+
+/// `a * b`, where `a` and `b` are integers with `a_decimals` and `b_decimals`
+/// decimal places, keeping `a_decimals`. Confidences combine to first order:
+/// `a_conf * b + a * b_conf`, rounded up.
+pub fn multiply(
+    (a, a_conf): (i64, u64),
+    (b, b_conf, b_decimals): (i64, u64, u32),
+    mode: RoundingMode,
+) -> Result<(i64, u64), String> {
+    if b_decimals > MAX_POW10 {
+        return Err(format!("Multiplier has {} decimals", b_decimals));
+    }
+    let divisor = 10i128.pow(b_decimals);
+
+    let value = fixed_point::divide(a as i128 * b as i128, divisor, mode);
+    let value = i64::try_from(value).map_err(|_| format!("{} × {} is out of range", a, b))?;
+
+    let conf = (a_conf as u128 * b.unsigned_abs() as u128)
+        .checked_add(a.unsigned_abs() as u128 * b_conf as u128)
+        .and_then(|conf| i128::try_from(conf).ok())
+        .map(|conf| fixed_point::divide(conf, divisor, RoundingMode::Ceiling))
+        .and_then(|conf| u64::try_from(conf).ok())
+        .ok_or_else(|| format!("Confidence of {} × {} is out of range", a, b))?;
+
+    Ok((value, conf))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invert_usd_jpy() {
+        // USD/JPY 149.8765 ± 0.0150 becomes JPY/USD 0.006672 at 6 decimals.
+        let (value, conf) = invert(1_498_765, -4, 150, 6, RoundingMode::HalfEven).unwrap();
+        assert_eq!((value, conf), (6_672, 1));
+
+        let (value, conf) = invert(1_498_765, -4, 150, 12, RoundingMode::HalfEven).unwrap();
+        assert_eq!((value, conf), (6_672_160_078, 667_766));
+    }
+
+    #[test]
+    fn test_invert_rejects_unusable_prices() {
+        assert!(invert(0, -8, 0, 6, RoundingMode::HalfEven).is_err());
+        assert!(invert(-5, -8, 0, 6, RoundingMode::HalfEven).is_err());
+        assert!(invert(5_000_000, 3, 0, 6, RoundingMode::HalfEven).unwrap_err().contains("rounds to zero"));
+    }
+
+    #[test]
+    fn test_multiply_steth_by_eth_usd() {
+        // stETH/ETH 0.999512 ± 0.000100 × ETH/USD 2623.456789 ± 1.200000.
+        let (value, conf) = multiply((999_512, 100), (2_623_456_789, 1_200_000, 6), RoundingMode::HalfEven).unwrap();
+
+        assert_eq!(value, 2_622_176_542);
+        assert_eq!(conf, 1_461_761);
+    }
+
+    #[test]
+    fn test_multiply_keeps_the_first_operand_decimals() {
+        let (value, _) = multiply((150_000_000, 0), (2_000, 0, 3), RoundingMode::HalfEven).unwrap();
+        assert_eq!(value, 300_000_000);
+
+        assert!(multiply((i64::MAX, 0), (i64::MAX, 0, 0), RoundingMode::HalfEven).is_err());
+    }
+}
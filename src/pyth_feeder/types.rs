@@ -4,11 +4,116 @@ use ic_stable_structures::Storable;
 use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 
+use crate::evm_rpc::{self, EvmChain};
+use crate::fixed_point::RoundingMode;
+use crate::gating::{GatingRules, Rejection};
+use crate::pyth::PublisherStats;
+use crate::sol_rpc::{CommitmentLevel, ConsensusStrategy, RpcSources, SolanaCluster};
+
 #[derive(Debug, Clone, CandidType, Serialize, Deserialize)]
 pub struct PythFeed {
     pub symbol: String,
     pub account: String,
     pub enabled: bool,
+    pub push_rules: Option<PushRules>,
+    pub quarantine: Option<QuarantineInfo>,
+    pub gating: Option<GatingRules>,
+    pub source: Option<FeedSource>,
+    /// Decides which feeds a cycle budget skips first; defaults to `Normal`.
+    pub priority: Option<FeedPriority>,
+    pub transform: Option<FeedTransform>,
+}
+
+/// How a feed's price is turned into what is pushed. New versions are added
+/// as new variants so stored feeds keep decoding.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Serialize, Deserialize)]
+pub enum FeedTransform {
+    V1(TransformV1),
+}
+
+/// Applied in order: the price is inverted, multiplied by `multiply_by`'s
+/// price from the same round, and scaled to `target_decimals` (6 by
+/// default) with `rounding`. It is then pushed as `oracle_symbol`, which
+/// defaults to the feed's symbol; feeds sharing an oracle symbol are
+/// aggregated into one price.
+#[derive(Debug, Clone, Default, PartialEq, Eq, CandidType, Serialize, Deserialize)]
+pub struct TransformV1 {
+    pub oracle_symbol: Option<String>,
+    pub invert: bool,
+    pub multiply_by: Option<String>,
+    pub target_decimals: Option<u32>,
+    pub rounding: Option<RoundingMode>,
+}
+
+/// Feeds are fetched from `High` to `Low`; when a round's cycle budget runs
+/// out, the remaining, lower-priority feeds wait for a later round.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, CandidType, Serialize, Deserialize)]
+pub enum FeedPriority {
+    High,
+    #[default]
+    Normal,
+    Low,
+}
+
+/// Where a feed's price is read from. Feeds without a source read `account`
+/// through the SOL RPC canister.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Serialize, Deserialize)]
+pub enum FeedSource {
+    SolanaAccount,
+    /// A Switchboard on-demand pull feed at `account`, read through the SOL
+    /// RPC canister like Pyth accounts.
+    SwitchboardAccount,
+    Hermes { feed_id: String },
+    /// A Chainlink aggregator read through the EVM RPC canister. Answers
+    /// older than `max_age_secs` (default one hour) are rejected.
+    Chainlink {
+        chain: EvmChain,
+        aggregator: String,
+        max_age_secs: Option<u64>,
+    },
+    /// Tickers from exchange REST endpoints configured in
+    /// `FeederConfig::exchanges`. Each ticker that answers becomes a
+    /// separate price, tagged with its endpoint's name.
+    Exchange { tickers: Vec<ExchangeTicker> },
+}
+
+/// One market on one exchange, e.g. endpoint `binance` and pair `BTCUSDT`.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Serialize, Deserialize)]
+pub struct ExchangeTicker {
+    pub endpoint: String,
+    pub pair: String,
+}
+
+/// Why and when a feed was disabled automatically.
+#[derive(Debug, Clone, CandidType, Serialize, Deserialize)]
+pub struct QuarantineInfo {
+    pub reason: String,
+    pub quarantined_at: u64,
+    pub consecutive_failures: u32,
+}
+
+/// When a freshly fetched price is worth sending to the oracle.
+///
+/// A price is pushed if it moved at least `deviation_bps` away from the last
+/// pushed value, or if `heartbeat_secs` have passed since that push. Feeds
+/// without rules push every round.
+#[derive(Debug, Clone, CandidType, Serialize, Deserialize)]
+pub struct PushRules {
+    pub deviation_bps: u32,
+    pub heartbeat_secs: u64,
+}
+
+#[derive(Debug, Clone, CandidType, Serialize, Deserialize)]
+pub struct LastPush {
+    pub price: i64,
+    pub timestamp: u64,
+}
+
+/// Keys what each oracle target was last sent for a symbol.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, CandidType, Serialize, Deserialize)]
+pub struct TargetSymbol {
+    pub target: String,
+    pub symbol: String,
 }
 
 #[derive(Debug, Clone, CandidType, Serialize, Deserialize)]
@@ -18,6 +123,52 @@ pub struct FeedUpdateResult {
     pub error: Option<String>,
     pub price: Option<f64>,
     pub confidence: Option<f64>,
+    pub pushed: bool,
+    pub latency_ns: u64,
+    pub rejection: Option<Rejection>,
+}
+
+#[derive(Debug, Clone, CandidType, Serialize, Deserialize)]
+pub struct RoundRecord {
+    pub id: u64,
+    pub started_at: u64,
+    pub finished_at: u64,
+    pub results: Vec<FeedUpdateResult>,
+    pub aggregates: Option<Vec<AggregateRecord>>,
+    /// Feeds skipped because the cycle budget ran out.
+    pub skipped: Option<Vec<String>>,
+    pub deliveries: Option<Vec<TargetDelivery>>,
+}
+
+/// How the prices for one oracle symbol were combined in a round.
+#[derive(Debug, Clone, CandidType, Serialize, Deserialize)]
+pub struct AggregateRecord {
+    pub symbol: String,
+    pub kept: Vec<String>,
+    pub dropped: Vec<String>,
+    pub spread_bps: u64,
+    pub diverged: bool,
+    pub pushed: bool,
+    pub error: Option<String>,
+    /// Feeds left out because their price could not be brought to the
+    /// symbol's decimals, with the reason.
+    pub unscaled: Option<Vec<(String, String)>>,
+    /// Why an aggregate that was computed was not pushed.
+    pub skipped: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, CandidType, Serialize, Deserialize)]
+pub struct FeedHealth {
+    pub attempts: u64,
+    pub successes: u64,
+    pub success_rate: f64,
+    pub consecutive_failures: u32,
+    pub last_attempt_time: u64,
+    pub last_good_price: Option<f64>,
+    pub last_good_time: Option<u64>,
+    pub last_error: Option<String>,
+    /// From the last fetch that reported its publishers.
+    pub publishers: Option<PublisherStats>,
 }
 
 #[derive(Debug, Clone, CandidType, Serialize, Deserialize)]
@@ -28,6 +179,16 @@ pub struct FeederMetrics {
     pub last_update_time: u64,
     pub active_feeds: u32,
     pub timer_running: bool,
+    /// Cycles kept by rounds' outcalls and RPC canisters, after refunds.
+    pub cycles_spent: Option<u128>,
+    pub cycles_spent_today: Option<u128>,
+    /// Days since the epoch that `cycles_spent_today` covers.
+    pub cycles_day: Option<u64>,
+    pub last_round_cycles: Option<u128>,
+    /// Feeds left unfetched because a cycle budget was exhausted.
+    pub skipped_for_budget: Option<u64>,
+    /// Cycles kept by calls made outside rounds, such as product discovery.
+    pub cycles_spent_outside_rounds: Option<u128>,
 }
 
 #[derive(Debug, Clone, CandidType, Serialize, Deserialize)]
@@ -35,17 +196,214 @@ pub struct FeederConfig {
     pub oracle_canister_id: Principal,
     pub sol_rpc_canister_id: Principal,
     pub update_interval_secs: u64,
+    pub solana_rpc: Option<SolanaRpcSettings>,
+    pub quarantine: Option<QuarantineSettings>,
+    pub hermes: Option<HermesSettings>,
+    pub evm_rpc: Option<EvmRpcSettings>,
+    pub exchanges: Option<Vec<ExchangeEndpoint>>,
+    pub aggregation: Option<AggregationSettings>,
+    pub fetch: Option<FetchSettings>,
+    /// Oracles to push to. When unset, `oracle_canister_id` is the only one.
+    pub oracle_targets: Option<Vec<OracleTarget>>,
+    /// Registers a feed's oracle symbol on the targets when the feed is
+    /// added or enabled.
+    pub register_symbols: Option<bool>,
+}
+
+/// An oracle canister prices are pushed to. With `symbols` set, only those
+/// oracle symbols are pushed to it.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Serialize, Deserialize)]
+pub struct OracleTarget {
+    pub name: String,
+    pub canister_id: Principal,
+    pub enabled: bool,
+    pub symbols: Option<Vec<String>>,
+}
+
+/// How a round's push to one target went.
+#[derive(Debug, Clone, CandidType, Serialize, Deserialize)]
+pub struct TargetDelivery {
+    pub target: String,
+    pub pushed: u32,
+    pub version: Option<u64>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, CandidType, Serialize, Deserialize)]
+pub struct TargetStatus {
+    pub deliveries: u64,
+    pub failures: u64,
+    pub consecutive_failures: u32,
+    pub last_attempt_time: u64,
+    pub last_success_time: Option<u64>,
+    pub last_version: Option<u64>,
+    pub last_error: Option<String>,
+}
+
+/// Up to `max_concurrent_batches` batches are in flight at once. A batch is
+/// only sent if the cycles it attaches fit in what is left of both budgets;
+/// the first batch that does not fit and every batch after it are skipped.
+#[derive(Debug, Clone, CandidType, Serialize, Deserialize)]
+pub struct FetchSettings {
+    pub max_concurrent_batches: u32,
+    pub cycles_per_round: Option<u128>,
+    pub cycles_per_day: Option<u128>,
+}
+
+impl Default for FetchSettings {
+    fn default() -> Self {
+        Self {
+            max_concurrent_batches: 4,
+            cycles_per_round: None,
+            cycles_per_day: None,
+        }
+    }
+}
+
+/// How feeds sharing an oracle symbol are combined: quotes further than
+/// `max_outlier_bps` from the median are dropped, at least `min_sources`
+/// must remain, and a spread wider than `divergence_bps` flags the round.
+#[derive(Debug, Clone, CandidType, Serialize, Deserialize)]
+pub struct AggregationSettings {
+    pub min_sources: u32,
+    pub max_outlier_bps: u32,
+    pub divergence_bps: u32,
+}
+
+impl Default for AggregationSettings {
+    fn default() -> Self {
+        Self {
+            min_sources: 1,
+            max_outlier_bps: 200,
+            divergence_bps: 100,
+        }
+    }
+}
+
+/// Failing feeds are retried with exponential backoff, capped at
+/// `max_backoff_secs`, and disabled after `max_consecutive_failures`.
+#[derive(Debug, Clone, CandidType, Serialize, Deserialize)]
+pub struct QuarantineSettings {
+    pub max_consecutive_failures: u32,
+    pub max_backoff_secs: u64,
+}
+
+impl Default for QuarantineSettings {
+    fn default() -> Self {
+        Self {
+            max_consecutive_failures: 10,
+            max_backoff_secs: 3600,
+        }
+    }
+}
+
+#[derive(Debug, Clone, CandidType, Serialize, Deserialize)]
+pub struct SolanaRpcSettings {
+    pub sources: RpcSources,
+    pub commitment: Option<CommitmentLevel>,
+    pub consensus: Option<ConsensusStrategy>,
+    /// First Pyth mapping account product discovery reads; defaults to
+    /// mainnet's.
+    pub mapping_account: Option<String>,
+}
+
+impl Default for SolanaRpcSettings {
+    fn default() -> Self {
+        Self {
+            sources: RpcSources::Default(SolanaCluster::Mainnet),
+            commitment: None,
+            consensus: None,
+            mapping_account: None,
+        }
+    }
+}
+
+/// Where Hermes feeds are fetched from. `base_url` can point at a local
+/// stub; `max_response_bytes` defaults to an estimate based on the number of
+/// feeds requested.
+///
+/// With a `guardian_set`, Hermes prices are only taken from accumulator
+/// updates signed by a quorum of those guardians, so Hermes itself no longer
+/// needs to be trusted.
+#[derive(Debug, Clone, CandidType, Serialize, Deserialize)]
+pub struct HermesSettings {
+    pub base_url: String,
+    pub max_response_bytes: Option<u64>,
+    pub guardian_set: Option<GuardianSetConfig>,
+}
+
+impl Default for HermesSettings {
+    fn default() -> Self {
+        Self {
+            base_url: "https://hermes.pyth.network".to_string(),
+            max_response_bytes: None,
+            guardian_set: None,
+        }
+    }
+}
+
+/// The EVM RPC canister Chainlink feeds are read through; `canister_id` can
+/// point at a local mock.
+#[derive(Debug, Clone, CandidType, Serialize, Deserialize)]
+pub struct EvmRpcSettings {
+    pub canister_id: Principal,
+    pub consensus: Option<ConsensusStrategy>,
+}
+
+impl Default for EvmRpcSettings {
+    fn default() -> Self {
+        Self {
+            canister_id: evm_rpc::default_canister_id(),
+            consensus: None,
+        }
+    }
+}
+
+/// An exchange candle endpoint. `url_template` has `{pair}` replaced by the
+/// ticker's pair and `{start}`, `{start_ms}`, `{end}` or `{end_ms}` by the
+/// bounds of the last closed candle of `candle_secs` (60 by default);
+/// `price_path` and `volume_path` locate the fields in the JSON response,
+/// e.g. `$[0][4]` (see `exchange::extract`).
+#[derive(Debug, Clone, CandidType, Serialize, Deserialize)]
+pub struct ExchangeEndpoint {
+    pub name: String,
+    pub url_template: String,
+    pub price_path: String,
+    pub volume_path: Option<String>,
+    pub max_response_bytes: Option<u64>,
+    pub candle_secs: Option<u64>,
+}
+
+/// A Wormhole guardian set: its index and the guardians' 20-byte addresses
+/// in hex, in guardian order.
+#[derive(Debug, Clone, CandidType, Serialize, Deserialize)]
+pub struct GuardianSetConfig {
+    pub index: u32,
+    pub addresses: Vec<String>,
+}
+
+/// Access levels, each including everything below it.
+///
+/// Viewers can read configuration and the role list, operators can also run
+/// the fetch loop and toggle feeds, admins can change anything.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, CandidType, Serialize, Deserialize)]
+pub enum Role {
+    Viewer,
+    Operator,
+    Admin,
 }
 
 #[derive(Debug, Clone, CandidType, Serialize, Deserialize)]
 pub struct InitArgs {
+    pub admin: Option<Principal>,
     pub oracle_canister_id: Option<Principal>,
     pub sol_rpc_canister_id: Principal,
     pub update_interval_secs: Option<u64>,
+    pub solana_rpc: Option<SolanaRpcSettings>,
 }
 
 impl Storable for PythFeed {
-    fn to_bytes(&self) -> Cow<[u8]> {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
         Cow::Owned(Encode!(self).unwrap())
     }
 
@@ -56,8 +414,139 @@ impl Storable for PythFeed {
     const BOUND: Bound = Bound::Unbounded;
 }
 
-impl Storable for FeederMetrics {
-    fn to_bytes(&self) -> Cow<[u8]> {
+impl PythFeed {
+    pub fn oracle_symbol(&self) -> &str {
+        match &self.transform {
+            Some(FeedTransform::V1(TransformV1 { oracle_symbol: Some(renamed), .. })) => renamed,
+            _ => &self.symbol,
+        }
+    }
+
+    /// The feed's transform; feeds without one are pushed unchanged.
+    pub fn transform(&self) -> TransformV1 {
+        match &self.transform {
+            Some(FeedTransform::V1(transform)) => transform.clone(),
+            None => TransformV1::default(),
+        }
+    }
+
+    pub fn hermes_feed_id(&self) -> Option<&str> {
+        match &self.source {
+            Some(FeedSource::Hermes { feed_id }) => Some(feed_id),
+            _ => None,
+        }
+    }
+}
+
+impl PushRules {
+    pub fn should_push(&self, last: Option<&LastPush>, price: i64, now: u64) -> bool {
+        let last = match last {
+            Some(last) => last,
+            None => return true,
+        };
+
+        if now.saturating_sub(last.timestamp) >= self.heartbeat_secs.saturating_mul(1_000_000_000) {
+            return true;
+        }
+
+        if last.price == 0 {
+            return true;
+        }
+
+        let moved = (price as i128 - last.price as i128).abs();
+        moved * 10_000 >= self.deviation_bps as i128 * (last.price as i128).abs()
+    }
+}
+
+impl FeedHealth {
+    pub fn record(&mut self, result: &FeedUpdateResult, now: u64) {
+        self.attempts += 1;
+        self.last_attempt_time = now;
+
+        if result.success {
+            self.successes += 1;
+            self.consecutive_failures = 0;
+            self.last_good_price = result.price;
+            self.last_good_time = Some(now);
+        } else {
+            // A rejected price (market closed, stale, ...) is not a fault of
+            // the feed, so it must not push the feed towards quarantine.
+            if result.rejection.is_none() {
+                self.consecutive_failures += 1;
+            }
+            self.last_error = result.error.clone();
+        }
+
+        self.success_rate = self.successes as f64 / self.attempts as f64;
+    }
+
+    /// Whether the feed should be fetched this round. After `n` consecutive
+    /// failures the feed waits `interval * 2^(n-1)`, at most `max_backoff_secs`.
+    pub fn is_due(&self, now: u64, interval_secs: u64, max_backoff_secs: u64) -> bool {
+        if self.consecutive_failures <= 1 {
+            return true;
+        }
+
+        let exponent = (self.consecutive_failures - 1).min(32);
+        let backoff_secs = interval_secs
+            .saturating_mul(1u64 << exponent)
+            .min(max_backoff_secs.max(interval_secs));
+
+        now >= self.last_attempt_time.saturating_add(backoff_secs.saturating_mul(1_000_000_000))
+    }
+}
+
+impl FeederConfig {
+    /// The oracles to push to, enabled or not.
+    pub fn targets(&self) -> Vec<OracleTarget> {
+        match &self.oracle_targets {
+            Some(targets) => targets.clone(),
+            None if self.oracle_canister_id == Principal::anonymous() => vec![],
+            None => vec![OracleTarget {
+                name: "default".to_string(),
+                canister_id: self.oracle_canister_id,
+                enabled: true,
+                symbols: None,
+            }],
+        }
+    }
+}
+
+impl OracleTarget {
+    pub fn accepts(&self, symbol: &str) -> bool {
+        self.symbols.as_ref().is_none_or(|symbols| symbols.iter().any(|s| s == symbol))
+    }
+
+    /// Whether this target should be sent `price` for `symbol`, judged
+    /// against what it was last sent so that a target which missed pushes
+    /// catches up regardless of the others.
+    pub fn is_due(&self, symbol: &str, price: i64, rules: Option<&PushRules>, last: Option<&LastPush>, now: u64) -> bool {
+        self.accepts(symbol) && rules.is_none_or(|rules| rules.should_push(last, price, now))
+    }
+}
+
+impl TargetStatus {
+    pub fn record(&mut self, delivery: &TargetDelivery, now: u64) {
+        self.deliveries += 1;
+        self.last_attempt_time = now;
+
+        match (&delivery.error, delivery.version) {
+            (None, version) => {
+                self.consecutive_failures = 0;
+                self.last_success_time = Some(now);
+                self.last_version = version;
+            }
+            (Some(error), _) => {
+                self.failures += 1;
+                self.consecutive_failures += 1;
+                self.last_error = Some(error.clone());
+            }
+        }
+    }
+}
+
+impl Storable for RoundRecord {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
         Cow::Owned(Encode!(self).unwrap())
     }
 
@@ -65,14 +554,59 @@ impl Storable for FeederMetrics {
         Decode!(bytes.as_ref(), Self).unwrap()
     }
 
-    const BOUND: Bound = Bound::Bounded {
-        max_size: 200,
-        is_fixed_size: false,
-    };
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+impl Storable for TargetStatus {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
 }
 
-impl Storable for FeederConfig {
-    fn to_bytes(&self) -> Cow<[u8]> {
+impl Storable for FeedHealth {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+impl Storable for LastPush {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+impl Storable for TargetSymbol {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+impl Storable for Role {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
         Cow::Owned(Encode!(self).unwrap())
     }
 
@@ -81,7 +615,381 @@ impl Storable for FeederConfig {
     }
 
     const BOUND: Bound = Bound::Bounded {
-        max_size: 200,
+        max_size: 64,
         is_fixed_size: false,
     };
 }
+
+/// Schema version of the config and metrics cells and of the feeds. Bump it
+/// and add a step to `state::migrate` for changes that need more than a new
+/// `opt` field.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// Marks a cell written with its schema version. Cells written before
+/// versioning hold bare Candid, which starts with `DIDL`, and read as
+/// version 1.
+const VERSIONED_MAGIC: &[u8; 4] = b"VCEL";
+
+/// A stable cell value tagged with the schema version it was written under.
+#[derive(Debug, Clone)]
+pub struct Versioned<T> {
+    pub version: u32,
+    pub value: T,
+}
+
+impl<T> Versioned<T> {
+    pub fn current(value: T) -> Self {
+        Self { version: SCHEMA_VERSION, value }
+    }
+}
+
+impl<T> Storable for Versioned<T>
+where
+    T: CandidType + for<'de> Deserialize<'de>,
+{
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let mut bytes = VERSIONED_MAGIC.to_vec();
+        bytes.extend(self.version.to_le_bytes());
+        bytes.extend(candid::encode_one(&self.value).unwrap());
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        match bytes.strip_prefix(VERSIONED_MAGIC) {
+            Some(rest) if rest.len() >= 4 => Self {
+                version: u32::from_le_bytes(rest[..4].try_into().expect("4 bytes")),
+                value: candid::decode_one(&rest[4..]).unwrap(),
+            },
+            _ => Self {
+                version: 1,
+                value: candid::decode_one(&bytes).unwrap(),
+            },
+        }
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+impl FeederConfig {
+    /// Rejects settings the feeder cannot run with.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.update_interval_secs == 0 {
+            return Err("update_interval_secs must be at least 1".to_string());
+        }
+        if self.sol_rpc_canister_id == Principal::anonymous() {
+            return Err("sol_rpc_canister_id must not be anonymous".to_string());
+        }
+        if self.oracle_targets.is_none() && self.oracle_canister_id == Principal::anonymous() {
+            return Err("Set oracle_canister_id or oracle_targets".to_string());
+        }
+        if self.evm_rpc.as_ref().is_some_and(|evm_rpc| evm_rpc.canister_id == Principal::anonymous()) {
+            return Err("evm_rpc.canister_id must not be anonymous".to_string());
+        }
+        if self.quarantine.as_ref().is_some_and(|q| q.max_consecutive_failures == 0) {
+            return Err("quarantine.max_consecutive_failures must be at least 1".to_string());
+        }
+        if let Some(fetch) = &self.fetch {
+            if fetch.max_concurrent_batches == 0 {
+                return Err("max_concurrent_batches must be at least 1".to_string());
+            }
+            if fetch.cycles_per_round == Some(0) || fetch.cycles_per_day == Some(0) {
+                return Err("Cycle budgets must be unset or above 0".to_string());
+            }
+        }
+
+        let targets = self.oracle_targets.as_deref().unwrap_or_default();
+        for (i, target) in targets.iter().enumerate() {
+            if target.name.is_empty() || targets[..i].iter().any(|t| t.name == target.name) {
+                return Err("Oracle targets need unique, non-empty names".to_string());
+            }
+            if target.canister_id == Principal::anonymous() {
+                return Err(format!("Oracle target {} has no canister id", target.name));
+            }
+        }
+
+        crate::exchange::validate_endpoints(self.exchanges.as_deref().unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: u64 = 1_000_000_000;
+
+    fn rules() -> PushRules {
+        PushRules {
+            deviation_bps: 50,
+            heartbeat_secs: 60,
+        }
+    }
+
+    #[test]
+    fn test_roles_are_ordered_by_privilege() {
+        assert!(Role::Admin > Role::Operator);
+        assert!(Role::Operator > Role::Viewer);
+    }
+
+    #[test]
+    fn test_feed_health_tracks_streaks_and_last_good_price() {
+        let ok = FeedUpdateResult {
+            symbol: "BTC".to_string(),
+            success: true,
+            error: None,
+            price: Some(67_000.0),
+            confidence: Some(12.0),
+            pushed: true,
+            latency_ns: 0,
+            rejection: None,
+        };
+        let failed = FeedUpdateResult {
+            success: false,
+            error: Some("Account not found".to_string()),
+            price: None,
+            confidence: None,
+            pushed: false,
+            ..ok.clone()
+        };
+
+        let mut health = FeedHealth::default();
+        health.record(&ok, 1);
+        health.record(&failed, 2);
+        health.record(&failed, 3);
+
+        assert_eq!(health.consecutive_failures, 2);
+        assert_eq!(health.last_good_price, Some(67_000.0));
+        assert_eq!(health.last_good_time, Some(1));
+        assert!((health.success_rate - 1.0 / 3.0).abs() < 1e-9);
+
+        let rejected = FeedUpdateResult {
+            rejection: Some(Rejection::NotTrading { status: crate::pyth::PriceStatus::Halted }),
+            ..failed.clone()
+        };
+        health.record(&rejected, 4);
+        assert_eq!(health.consecutive_failures, 2);
+
+        health.record(&ok, 5);
+        assert_eq!(health.consecutive_failures, 0);
+    }
+
+    #[test]
+    fn test_backoff_doubles_and_is_capped() {
+        let health = FeedHealth {
+            consecutive_failures: 3,
+            last_attempt_time: 0,
+            ..FeedHealth::default()
+        };
+
+        // three failures at a 10s interval wait 40s
+        assert!(!health.is_due(39 * SECOND, 10, 3600));
+        assert!(health.is_due(40 * SECOND, 10, 3600));
+
+        let health = FeedHealth {
+            consecutive_failures: 30,
+            ..health
+        };
+        assert!(health.is_due(3600 * SECOND, 10, 3600));
+
+        // A backoff too long to express in nanoseconds keeps the feed waiting.
+        assert!(!health.is_due(1_700_000_000 * SECOND, 1_000_000, u64::MAX / 1_000));
+    }
+
+    #[test]
+    fn test_first_price_is_always_pushed() {
+        assert!(rules().should_push(None, 100_000_000, 0));
+    }
+
+    #[test]
+    fn test_small_move_within_heartbeat_is_skipped() {
+        let last = LastPush { price: 100_000_000, timestamp: 0 };
+        assert!(!rules().should_push(Some(&last), 100_400_000, 30 * SECOND));
+    }
+
+    #[test]
+    fn test_deviation_triggers_push() {
+        let last = LastPush { price: 100_000_000, timestamp: 0 };
+        assert!(rules().should_push(Some(&last), 99_500_000, 30 * SECOND));
+    }
+
+    #[test]
+    fn test_heartbeat_triggers_push() {
+        let last = LastPush { price: 100_000_000, timestamp: 0 };
+        assert!(rules().should_push(Some(&last), 100_000_000, 60 * SECOND));
+    }
+
+    #[test]
+    fn test_huge_heartbeat_never_fires() {
+        let rules = PushRules { heartbeat_secs: u64::MAX / 1_000, ..rules() };
+        let last = LastPush { price: 100_000_000, timestamp: 0 };
+        assert!(!rules.should_push(Some(&last), 100_000_000, 1_700_000_000 * SECOND));
+    }
+
+    #[test]
+    fn test_single_oracle_id_is_the_default_target() {
+        let oracle = Principal::from_text("ryjl3-tyaaa-aaaaa-aaaba-cai").unwrap();
+        let mut config = FeederConfig {
+            oracle_canister_id: Principal::anonymous(),
+            sol_rpc_canister_id: Principal::anonymous(),
+            update_interval_secs: 10,
+            solana_rpc: None,
+            quarantine: None,
+            hermes: None,
+            evm_rpc: None,
+            exchanges: None,
+            aggregation: None,
+            fetch: None,
+            oracle_targets: None,
+            register_symbols: None,
+        };
+        assert!(config.targets().is_empty());
+
+        config.oracle_canister_id = oracle;
+        assert_eq!(config.targets()[0].name, "default");
+
+        let staging = OracleTarget {
+            name: "staging".to_string(),
+            canister_id: oracle,
+            enabled: true,
+            symbols: Some(vec!["BTC".to_string()]),
+        };
+        config.oracle_targets = Some(vec![staging.clone()]);
+        assert_eq!(config.targets(), vec![staging.clone()]);
+        assert!(staging.accepts("BTC") && !staging.accepts("ETH"));
+    }
+
+    #[test]
+    fn test_failed_target_catches_up_on_its_own() {
+        let target = |name: &str| OracleTarget {
+            name: name.to_string(),
+            canister_id: Principal::anonymous(),
+            enabled: true,
+            symbols: None,
+        };
+        let (prod, staging) = (target("prod"), target("staging"));
+        let mut last: std::collections::HashMap<String, LastPush> = Default::default();
+        let mut round = |now: u64, price: i64, failing: &[&str]| -> Vec<String> {
+            let mut pushed = vec![];
+            for t in [&prod, &staging] {
+                if t.is_due("BTC", price, Some(&rules()), last.get(&t.name), now) && !failing.contains(&t.name.as_str()) {
+                    last.insert(t.name.clone(), LastPush { price, timestamp: now });
+                    pushed.push(t.name.clone());
+                }
+            }
+            pushed
+        };
+
+        // Staging is down for the first push, so only prod records it.
+        assert_eq!(round(0, 100_000_000, &["staging"]), vec!["prod"]);
+        // Once back, staging is sent the unchanged price prod no longer needs.
+        assert_eq!(round(10 * SECOND, 100_000_000, &[]), vec!["staging"]);
+        assert!(round(20 * SECOND, 100_000_000, &[]).is_empty());
+        // Both are then due together again on the heartbeat.
+        assert_eq!(round(60 * SECOND, 100_000_000, &[]), vec!["prod"]);
+        assert_eq!(round(70 * SECOND, 100_000_000, &[]), vec!["staging"]);
+    }
+
+    #[test]
+    fn test_target_status_tracks_failure_streaks() {
+        let ok = TargetDelivery { target: "prod".to_string(), pushed: 3, version: Some(7), error: None };
+        let failed = TargetDelivery { pushed: 0, version: None, error: Some("Oracle call failed".to_string()), ..ok.clone() };
+
+        let mut status = TargetStatus::default();
+        status.record(&failed, 1);
+        status.record(&failed, 2);
+        assert_eq!((status.failures, status.consecutive_failures, status.last_success_time), (2, 2, None));
+
+        status.record(&ok, 3);
+        assert_eq!((status.deliveries, status.consecutive_failures), (3, 0));
+        assert_eq!((status.last_success_time, status.last_version), (Some(3), Some(7)));
+        assert_eq!(status.last_error.as_deref(), Some("Oracle call failed"));
+    }
+
+    #[test]
+    fn test_feeds_are_pushed_under_their_own_symbol_unless_renamed() {
+        let mut feed = PythFeed {
+            symbol: "USDJPY".to_string(),
+            account: String::new(),
+            enabled: true,
+            push_rules: None,
+            quarantine: None,
+            gating: None,
+            source: None,
+            priority: None,
+            transform: None,
+        };
+        assert_eq!(feed.oracle_symbol(), "USDJPY");
+        assert_eq!(feed.transform(), TransformV1::default());
+
+        let inverted = TransformV1 { oracle_symbol: Some("JPY/USD".to_string()), invert: true, ..TransformV1::default() };
+        feed.transform = Some(FeedTransform::V1(inverted));
+        assert_eq!(feed.oracle_symbol(), "JPY/USD");
+        assert!(feed.transform().invert);
+    }
+
+    fn config() -> FeederConfig {
+        FeederConfig {
+            oracle_canister_id: Principal::from_text("ryjl3-tyaaa-aaaaa-aaaba-cai").unwrap(),
+            sol_rpc_canister_id: Principal::from_text("tghme-zyaaa-aaaar-qarca-cai").unwrap(),
+            update_interval_secs: 10,
+            solana_rpc: None,
+            quarantine: None,
+            hermes: None,
+            evm_rpc: None,
+            exchanges: None,
+            aggregation: None,
+            fetch: None,
+            oracle_targets: None,
+            register_symbols: None,
+        }
+    }
+
+    #[test]
+    fn test_cells_written_before_versioning_read_as_version_1() {
+        let legacy = Encode!(&config()).unwrap();
+        let read = Versioned::<FeederConfig>::from_bytes(Cow::Owned(legacy));
+        assert_eq!((read.version, read.value.update_interval_secs), (1, 10));
+
+        let current = Versioned::current(config());
+        let read = Versioned::<FeederConfig>::from_bytes(current.to_bytes());
+        assert_eq!((read.version, read.value.oracle_canister_id), (SCHEMA_VERSION, config().oracle_canister_id));
+    }
+
+    #[test]
+    fn test_validate_rejects_nonsensical_config() {
+        assert_eq!(config().validate(), Ok(()));
+
+        let invalid = [
+            FeederConfig { update_interval_secs: 0, ..config() },
+            FeederConfig { sol_rpc_canister_id: Principal::anonymous(), ..config() },
+            FeederConfig { oracle_canister_id: Principal::anonymous(), ..config() },
+            FeederConfig {
+                quarantine: Some(QuarantineSettings { max_consecutive_failures: 0, max_backoff_secs: 60 }),
+                ..config()
+            },
+            FeederConfig {
+                fetch: Some(FetchSettings { cycles_per_day: Some(0), ..FetchSettings::default() }),
+                ..config()
+            },
+            FeederConfig {
+                oracle_targets: Some(vec![OracleTarget {
+                    name: "staging".to_string(),
+                    canister_id: Principal::anonymous(),
+                    enabled: true,
+                    symbols: None,
+                }]),
+                ..config()
+            },
+        ];
+        for config in invalid {
+            assert!(config.validate().is_err(), "{:?}", config);
+        }
+
+        // Targets stand in for the single oracle id.
+        let targets_only = FeederConfig {
+            oracle_canister_id: Principal::anonymous(),
+            oracle_targets: Some(vec![]),
+            ..config()
+        };
+        assert_eq!(targets_only.validate(), Ok(()));
+    }
+}
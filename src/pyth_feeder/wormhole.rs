@@ -0,0 +1,631 @@
+use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+use sha3::{Digest, Keccak256};
+
+use crate::pyth::{AccountFormat, ParsedPrice, PriceFeedMessage, PriceStatus};
+use crate::types::GuardianSetConfig;
+
+/// Wormhole chain id of Pythnet, where accumulator roots are emitted.
+pub const PYTHNET_CHAIN_ID: u16 = 26;
+
+/// Emitter of Pyth accumulator roots on Pythnet.
+pub const PYTHNET_ACCUMULATOR_EMITTER: [u8; 32] = [
+    225, 1, 250, 237, 172, 88, 81, 227, 43, 155, 35, 181, 249, 65, 26, 140, 43, 172, 74, 174, 62,
+    212, 221, 123, 129, 29, 209, 167, 46, 164, 170, 113,
+];
+
+const ACCUMULATOR_MAGIC: &[u8; 4] = b"PNAU";
+const ACCUMULATOR_MAJOR_VERSION: u8 = 1;
+const WORMHOLE_MERKLE_MAGIC: &[u8; 4] = b"AUWV";
+const WORMHOLE_MERKLE_UPDATE: u8 = 0;
+const PRICE_FEED_MESSAGE: u8 = 0;
+
+const MERKLE_LEAF_PREFIX: u8 = 0;
+const MERKLE_NODE_PREFIX: u8 = 1;
+
+pub type MerkleHash = [u8; 20];
+
+/// Guardian addresses a VAA must be signed by, parsed from `GuardianSetConfig`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GuardianSet {
+    pub index: u32,
+    pub addresses: Vec<[u8; 20]>,
+}
+
+impl GuardianSet {
+    pub fn from_config(config: &GuardianSetConfig) -> Result<Self, String> {
+        let addresses = config
+            .addresses
+            .iter()
+            .map(|address| {
+                let bytes = hex::decode(address.trim_start_matches("0x"))
+                    .map_err(|e| format!("Invalid guardian address {}: {}", address, e))?;
+                <[u8; 20]>::try_from(bytes.as_slice())
+                    .map_err(|_| format!("Guardian address {} is not 20 bytes", address))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        if addresses.is_empty() {
+            return Err("Guardian set is empty".to_string());
+        }
+
+        Ok(Self {
+            index: config.index,
+            addresses,
+        })
+    }
+
+    /// More than two thirds of the guardians, as Wormhole's core contract requires.
+    pub fn quorum(&self) -> usize {
+        self.addresses.len() * 2 / 3 + 1
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GuardianSignature {
+    pub guardian_index: u8,
+    /// `r || s || recovery id`
+    pub signature: [u8; 65],
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Vaa<'a> {
+    pub guardian_set_index: u32,
+    pub signatures: Vec<GuardianSignature>,
+    /// The signed part of the VAA, from `timestamp` to the end.
+    pub body: &'a [u8],
+    pub emitter_chain: u16,
+    pub emitter_address: [u8; 32],
+    pub sequence: u64,
+    pub payload: &'a [u8],
+}
+
+/// Price messages from an accumulator update that passed every check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifiedUpdate {
+    pub slot: u64,
+    pub messages: Vec<PriceFeedMessage>,
+}
+
+impl VerifiedUpdate {
+    pub fn parsed_prices(&self) -> Vec<ParsedPrice> {
+        self.messages
+            .iter()
+            .map(|message| ParsedPrice {
+                format: AccountFormat::Accumulator,
+                price: message.price,
+                conf: message.conf,
+                exponent: message.exponent,
+                status: PriceStatus::Trading,
+                publish_time: message.publish_time,
+                pub_slot: self.slot,
+                curr_slot: None,
+                num_publishers: None,
+                publishers: None,
+            })
+            .collect()
+    }
+}
+
+/// Big-endian reader; Wormhole and the accumulator format are both big-endian.
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, offset: 0 }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let bytes = self
+            .offset
+            .checked_add(len)
+            .and_then(|end| self.data.get(self.offset..end))
+            .ok_or_else(|| {
+                format!(
+                    "Update data too short: need {} bytes at offset {}, have {}",
+                    len,
+                    self.offset,
+                    self.data.len()
+                )
+            })?;
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        let mut out = [0u8; N];
+        out.copy_from_slice(self.take(N)?);
+        Ok(out)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        Ok(u16::from_be_bytes(self.array()?))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_be_bytes(self.array()?))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_be_bytes(self.array()?))
+    }
+
+    fn i32(&mut self) -> Result<i32, String> {
+        Ok(i32::from_be_bytes(self.array()?))
+    }
+
+    fn i64(&mut self) -> Result<i64, String> {
+        Ok(i64::from_be_bytes(self.array()?))
+    }
+
+    fn rest(&mut self) -> &'a [u8] {
+        let rest = &self.data[self.offset..];
+        self.offset = self.data.len();
+        rest
+    }
+}
+
+pub fn parse_vaa(data: &[u8]) -> Result<Vaa<'_>, String> {
+    let mut reader = Reader::new(data);
+
+    let version = reader.u8()?;
+    if version != 1 {
+        return Err(format!("Unsupported VAA version {}", version));
+    }
+
+    let guardian_set_index = reader.u32()?;
+    let signatures = (0..reader.u8()?)
+        .map(|_| {
+            Ok(GuardianSignature {
+                guardian_index: reader.u8()?,
+                signature: reader.array()?,
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let body = &data[reader.offset..];
+    let _timestamp = reader.u32()?;
+    let _nonce = reader.u32()?;
+    let emitter_chain = reader.u16()?;
+    let emitter_address = reader.array()?;
+    let sequence = reader.u64()?;
+    let _consistency_level = reader.u8()?;
+    let payload = reader.rest();
+
+    Ok(Vaa {
+        guardian_set_index,
+        signatures,
+        body,
+        emitter_chain,
+        emitter_address,
+        sequence,
+        payload,
+    })
+}
+
+/// Checks that a quorum of `guardians` signed the VAA body.
+///
+/// Signatures must come from distinct guardians listed in increasing index
+/// order, which is how Wormhole rules out counting one guardian twice.
+pub fn verify_vaa(vaa: &Vaa, guardians: &GuardianSet) -> Result<(), String> {
+    if vaa.guardian_set_index != guardians.index {
+        return Err(format!(
+            "VAA is signed by guardian set {}, but set {} is configured",
+            vaa.guardian_set_index, guardians.index
+        ));
+    }
+
+    if vaa.signatures.len() < guardians.quorum() {
+        return Err(format!(
+            "VAA has {} signatures, quorum is {}",
+            vaa.signatures.len(),
+            guardians.quorum()
+        ));
+    }
+
+    let digest = keccak256(&keccak256(vaa.body));
+    let mut previous: Option<u8> = None;
+
+    for signature in &vaa.signatures {
+        let index = signature.guardian_index;
+        if previous.is_some_and(|previous| index <= previous) {
+            return Err("VAA signatures are not in increasing guardian order".to_string());
+        }
+        previous = Some(index);
+
+        let expected = guardians
+            .addresses
+            .get(index as usize)
+            .ok_or_else(|| format!("VAA is signed by unknown guardian {}", index))?;
+
+        if recover_address(&digest, &signature.signature).as_ref() != Some(expected) {
+            return Err(format!("Invalid signature from guardian {}", index));
+        }
+    }
+
+    Ok(())
+}
+
+fn recover_address(digest: &[u8; 32], signature: &[u8; 65]) -> Option<[u8; 20]> {
+    let mut parsed = Signature::from_slice(&signature[..64]).ok()?;
+    let mut recovery_id = RecoveryId::from_byte(signature[64])?;
+
+    // k256 only accepts low-S signatures; flipping S flips the parity of R.
+    if let Some(normalized) = parsed.normalize_s() {
+        parsed = normalized;
+        recovery_id = RecoveryId::new(!recovery_id.is_y_odd(), recovery_id.is_x_reduced());
+    }
+
+    let key = VerifyingKey::recover_from_prehash(digest, &parsed, recovery_id).ok()?;
+    Some(ethereum_address(&key))
+}
+
+/// Guardians are identified like Ethereum accounts: the last 20 bytes of
+/// the Keccak hash of the uncompressed public key.
+pub fn ethereum_address(key: &VerifyingKey) -> [u8; 20] {
+    let point = key.to_encoded_point(false);
+    let hash = keccak256(&point.as_bytes()[1..]);
+
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&hash[12..]);
+    address
+}
+
+/// Verifies a Pyth accumulator update (`PNAU`) as served by Hermes.
+///
+/// The Wormhole VAA must come from the Pythnet accumulator emitter and carry
+/// a guardian quorum, and every price message must prove into the Merkle root
+/// the VAA signs. Anything that fails a check rejects the whole update.
+pub fn verify_accumulator_update(data: &[u8], guardians: &GuardianSet) -> Result<VerifiedUpdate, String> {
+    let mut reader = Reader::new(data);
+
+    if reader.take(4)? != ACCUMULATOR_MAGIC {
+        return Err("Not an accumulator update".to_string());
+    }
+    let major_version = reader.u8()?;
+    if major_version != ACCUMULATOR_MAJOR_VERSION {
+        return Err(format!("Unsupported accumulator version {}", major_version));
+    }
+    let _minor_version = reader.u8()?;
+    let trailing_header_len = reader.u8()? as usize;
+    reader.take(trailing_header_len)?;
+
+    let update_type = reader.u8()?;
+    if update_type != WORMHOLE_MERKLE_UPDATE {
+        return Err(format!("Unsupported accumulator update type {}", update_type));
+    }
+
+    let vaa_len = reader.u16()? as usize;
+    let vaa = parse_vaa(reader.take(vaa_len)?)?;
+    verify_vaa(&vaa, guardians)?;
+
+    if vaa.emitter_chain != PYTHNET_CHAIN_ID || vaa.emitter_address != PYTHNET_ACCUMULATOR_EMITTER {
+        return Err(format!(
+            "VAA was emitted by {}:{} rather than the Pythnet accumulator",
+            vaa.emitter_chain,
+            hex::encode(vaa.emitter_address)
+        ));
+    }
+
+    let (slot, root) = parse_merkle_root(vaa.payload)?;
+
+    let mut messages = Vec::new();
+    for _ in 0..reader.u8()? {
+        let message_len = reader.u16()? as usize;
+        let message = reader.take(message_len)?;
+        let proof = (0..reader.u8()?)
+            .map(|_| reader.array::<20>())
+            .collect::<Result<Vec<_>, String>>()?;
+
+        if !verify_merkle_proof(&root, message, &proof) {
+            return Err("Price message does not match the signed Merkle root".to_string());
+        }
+
+        if let Some(price) = parse_price_feed_message(message)? {
+            messages.push(price);
+        }
+    }
+
+    Ok(VerifiedUpdate { slot, messages })
+}
+
+fn parse_merkle_root(payload: &[u8]) -> Result<(u64, MerkleHash), String> {
+    let mut reader = Reader::new(payload);
+
+    if reader.take(4)? != WORMHOLE_MERKLE_MAGIC {
+        return Err("VAA payload is not a Wormhole Merkle root".to_string());
+    }
+    let update_type = reader.u8()?;
+    if update_type != WORMHOLE_MERKLE_UPDATE {
+        return Err(format!("Unsupported Merkle root type {}", update_type));
+    }
+
+    let slot = reader.u64()?;
+    let _ring_size = reader.u32()?;
+    let root = reader.array()?;
+
+    Ok((slot, root))
+}
+
+/// Decodes a price feed message, or returns `None` for other message types
+/// (such as TWAP messages) that share the accumulator.
+fn parse_price_feed_message(message: &[u8]) -> Result<Option<PriceFeedMessage>, String> {
+    let mut reader = Reader::new(message);
+
+    if reader.u8()? != PRICE_FEED_MESSAGE {
+        return Ok(None);
+    }
+
+    Ok(Some(PriceFeedMessage {
+        feed_id: reader.array()?,
+        price: reader.i64()?,
+        conf: reader.u64()?,
+        exponent: reader.i32()?,
+        publish_time: reader.i64()?,
+        prev_publish_time: reader.i64()?,
+        ema_price: reader.i64()?,
+        ema_conf: reader.u64()?,
+    }))
+}
+
+pub fn merkle_leaf(message: &[u8]) -> MerkleHash {
+    truncated_keccak(&[&[MERKLE_LEAF_PREFIX], message])
+}
+
+/// Children are hashed in sorted order, so proofs carry no left/right flags.
+pub fn merkle_node(a: &MerkleHash, b: &MerkleHash) -> MerkleHash {
+    let (low, high) = if a <= b { (a, b) } else { (b, a) };
+    truncated_keccak(&[&[MERKLE_NODE_PREFIX], low, high])
+}
+
+fn verify_merkle_proof(root: &MerkleHash, message: &[u8], proof: &[MerkleHash]) -> bool {
+    let computed = proof
+        .iter()
+        .fold(merkle_leaf(message), |hash, sibling| merkle_node(&hash, sibling));
+    &computed == root
+}
+
+fn keccak256(data: &[u8]) -> [u8; 32] {
+    Keccak256::digest(data).into()
+}
+
+fn truncated_keccak(parts: &[&[u8]]) -> MerkleHash {
+    let mut hasher = Keccak256::new();
+    for part in parts {
+        hasher.update(part);
+    }
+
+    let mut out = [0u8; 20];
+    out.copy_from_slice(&hasher.finalize()[..20]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k256::ecdsa::SigningKey;
+
+    const BTC: &str = "e62df6c8b4a85fe1a67db44dc12de5db330f7ac66b72dc658afedf0f4a415b43";
+    const SOL: &str = "ef0d8b6fda2ceba41da15d4095d1da392a0d2f8ed0c6c7bc0f4cfac8c280b56d";
+
+    const GUARDIAN_SET_INDEX: u32 = 4;
+    const RECORDED_UPDATE: &str = include_str!("fixtures/accumulator_update_test_guardians.hex");
+
+    /// Five guardians whose private keys are `[1; 32]` to `[5; 32]`.
+    fn guardian_keys() -> Vec<SigningKey> {
+        (1..=5u8)
+            .map(|i| SigningKey::from_slice(&[i; 32]).unwrap())
+            .collect()
+    }
+
+    fn guardian_set(keys: &[SigningKey]) -> GuardianSet {
+        GuardianSet {
+            index: GUARDIAN_SET_INDEX,
+            addresses: keys.iter().map(|key| ethereum_address(key.verifying_key())).collect(),
+        }
+    }
+
+    fn price_message(feed_id: &str, price: i64, conf: u64, publish_time: i64) -> Vec<u8> {
+        let mut message = vec![PRICE_FEED_MESSAGE];
+        message.extend(hex::decode(feed_id).unwrap());
+        message.extend(price.to_be_bytes());
+        message.extend(conf.to_be_bytes());
+        message.extend((-8i32).to_be_bytes());
+        message.extend(publish_time.to_be_bytes());
+        message.extend((publish_time - 1).to_be_bytes());
+        message.extend(price.to_be_bytes());
+        message.extend(conf.to_be_bytes());
+        message
+    }
+
+    fn messages() -> Vec<Vec<u8>> {
+        vec![
+            price_message(BTC, 6_734_512_345_678, 2_150_000_000, 1_728_000_000),
+            price_message(SOL, 14_523_456_789, 9_876_543, 1_728_000_003),
+            // A TWAP message, which the verifier proves but does not return.
+            vec![1; 89],
+        ]
+    }
+
+    /// Returns the root and one proof per leaf, padding to a power of two.
+    fn merkle_tree(messages: &[Vec<u8>]) -> (MerkleHash, Vec<Vec<MerkleHash>>) {
+        let mut level: Vec<MerkleHash> = messages.iter().map(|m| merkle_leaf(m)).collect();
+        level.resize(messages.len().next_power_of_two(), merkle_leaf(&[]));
+
+        let mut proofs = vec![Vec::new(); messages.len()];
+        let mut positions: Vec<usize> = (0..messages.len()).collect();
+
+        while level.len() > 1 {
+            for (proof, position) in proofs.iter_mut().zip(positions.iter_mut()) {
+                proof.push(level[*position ^ 1]);
+                *position /= 2;
+            }
+            level = level.chunks(2).map(|pair| merkle_node(&pair[0], &pair[1])).collect();
+        }
+
+        (level[0], proofs)
+    }
+
+    fn build_update(keys: &[SigningKey], signers: &[usize], emitter: [u8; 32], messages: &[Vec<u8>]) -> Vec<u8> {
+        let (root, proofs) = merkle_tree(messages);
+
+        let mut payload = WORMHOLE_MERKLE_MAGIC.to_vec();
+        payload.push(WORMHOLE_MERKLE_UPDATE);
+        payload.extend(289_345_120u64.to_be_bytes());
+        payload.extend(10_000u32.to_be_bytes());
+        payload.extend(root);
+
+        let mut body = Vec::new();
+        body.extend(1_728_000_004u32.to_be_bytes());
+        body.extend(0u32.to_be_bytes());
+        body.extend(PYTHNET_CHAIN_ID.to_be_bytes());
+        body.extend(emitter);
+        body.extend(42u64.to_be_bytes());
+        body.push(1);
+        body.extend(payload);
+
+        let digest = keccak256(&keccak256(&body));
+        let mut vaa = vec![1];
+        vaa.extend(GUARDIAN_SET_INDEX.to_be_bytes());
+        vaa.push(signers.len() as u8);
+        for &signer in signers {
+            let (signature, recovery_id) = keys[signer].sign_prehash_recoverable(&digest).unwrap();
+            vaa.push(signer as u8);
+            vaa.extend(signature.to_bytes());
+            vaa.push(recovery_id.to_byte());
+        }
+        vaa.extend(body);
+
+        let mut update = ACCUMULATOR_MAGIC.to_vec();
+        update.extend([ACCUMULATOR_MAJOR_VERSION, 0, 0, WORMHOLE_MERKLE_UPDATE]);
+        update.extend((vaa.len() as u16).to_be_bytes());
+        update.extend(vaa);
+        update.push(messages.len() as u8);
+        for (message, proof) in messages.iter().zip(proofs) {
+            update.extend((message.len() as u16).to_be_bytes());
+            update.extend(message);
+            update.push(proof.len() as u8);
+            update.extend(proof.concat());
+        }
+        update
+    }
+
+    fn recorded_update() -> Vec<u8> {
+        hex::decode(RECORDED_UPDATE.trim()).unwrap()
+    }
+
+    #[test]
+    #[ignore = "rewrites fixtures/accumulator_update_test_guardians.hex"]
+    fn regenerate_recorded_update() {
+        let update = build_update(&guardian_keys(), &[0, 1, 2, 4], PYTHNET_ACCUMULATOR_EMITTER, &messages());
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/accumulator_update_test_guardians.hex");
+        std::fs::write(path, hex::encode(update) + "\n").unwrap();
+    }
+
+    /// Verifies a Hermes response captured from mainnet with `encoding=hex`
+    /// against the guardian set that signed it, a `GuardianSetConfig` as
+    /// JSON. See `fixtures/README.md` for capturing both.
+    #[test]
+    #[ignore]
+    fn verify_captured_update() {
+        let read = |var: &str| std::fs::read(std::env::var(var).unwrap_or_else(|_| panic!("{} is not set", var))).unwrap();
+        let response: serde_json::Value = serde_json::from_slice(&read("HERMES_RESPONSE")).unwrap();
+        let config: crate::types::GuardianSetConfig = serde_json::from_slice(&read("GUARDIAN_SET")).unwrap();
+        let guardians = GuardianSet::from_config(&config).unwrap();
+
+        let data = response["binary"]["data"][0].as_str().expect("No binary update in the response");
+        let update = verify_accumulator_update(&hex::decode(data).unwrap(), &guardians).unwrap();
+
+        for (price, parsed) in update.parsed_prices().iter().zip(response["parsed"].as_array().unwrap()) {
+            assert_eq!(price.price.to_string(), parsed["price"]["price"].as_str().unwrap());
+            assert_eq!(price.conf.to_string(), parsed["price"]["conf"].as_str().unwrap());
+            assert_eq!(price.publish_time, parsed["price"]["publish_time"].as_i64().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_recorded_update_verifies() {
+        let update = verify_accumulator_update(&recorded_update(), &guardian_set(&guardian_keys())).unwrap();
+        let prices = update.parsed_prices();
+
+        assert_eq!(update.slot, 289_345_120);
+        assert_eq!(update.messages.len(), 2);
+        assert_eq!(hex::encode(update.messages[0].feed_id), BTC);
+        assert_eq!((prices[0].price, prices[0].conf, prices[0].exponent), (6_734_512_345_678, 2_150_000_000, -8));
+        assert_eq!(hex::encode(update.messages[1].feed_id), SOL);
+        assert_eq!((prices[1].price, prices[1].publish_time), (14_523_456_789, 1_728_000_003));
+        assert_eq!(prices[1].format, AccountFormat::Accumulator);
+    }
+
+    #[test]
+    fn test_builder_reproduces_recorded_update() {
+        let update = build_update(&guardian_keys(), &[0, 1, 2, 4], PYTHNET_ACCUMULATOR_EMITTER, &messages());
+        assert_eq!(update, recorded_update());
+    }
+
+    #[test]
+    fn test_quorum_and_signer_order() {
+        let keys = guardian_keys();
+        let guardians = guardian_set(&keys);
+        assert_eq!(guardians.quorum(), 4);
+
+        let too_few = build_update(&keys, &[0, 1, 2], PYTHNET_ACCUMULATOR_EMITTER, &messages());
+        assert_eq!(
+            verify_accumulator_update(&too_few, &guardians),
+            Err("VAA has 3 signatures, quorum is 4".to_string())
+        );
+
+        let repeated = build_update(&keys, &[0, 1, 1, 2], PYTHNET_ACCUMULATOR_EMITTER, &messages());
+        assert!(verify_accumulator_update(&repeated, &guardians).unwrap_err().contains("increasing"));
+    }
+
+    #[test]
+    fn test_rejects_foreign_signers_and_emitters() {
+        let keys = guardian_keys();
+
+        let other_set = GuardianSet { index: GUARDIAN_SET_INDEX + 1, ..guardian_set(&keys) };
+        assert!(verify_accumulator_update(&recorded_update(), &other_set).unwrap_err().contains("guardian set 4"));
+
+        let mut impostors = guardian_keys();
+        impostors[2] = SigningKey::from_slice(&[9; 32]).unwrap();
+        let forged = build_update(&impostors, &[0, 1, 2, 4], PYTHNET_ACCUMULATOR_EMITTER, &messages());
+        assert_eq!(
+            verify_accumulator_update(&forged, &guardian_set(&keys)),
+            Err("Invalid signature from guardian 2".to_string())
+        );
+
+        let elsewhere = build_update(&keys, &[0, 1, 2, 4], [7; 32], &messages());
+        assert!(verify_accumulator_update(&elsewhere, &guardian_set(&keys)).unwrap_err().contains("emitted by 26:0707"));
+    }
+
+    #[test]
+    fn test_rejects_tampering() {
+        let guardians = guardian_set(&guardian_keys());
+        let update = recorded_update();
+
+        // First byte of the first signature's `r`.
+        let mut bad_signature = update.clone();
+        bad_signature[17] ^= 1;
+        assert!(verify_accumulator_update(&bad_signature, &guardians).unwrap_err().contains("guardian 0"));
+
+        // Last byte of the BTC price, inside the first message.
+        let vaa_len = u16::from_be_bytes([update[8], update[9]]) as usize;
+        let price_offset = 10 + vaa_len + 1 + 2 + 1 + 32 + 7;
+        let mut bad_price = update.clone();
+        bad_price[price_offset] ^= 1;
+        assert_eq!(
+            verify_accumulator_update(&bad_price, &guardians),
+            Err("Price message does not match the signed Merkle root".to_string())
+        );
+
+        for len in [0, 9, 200, update.len() - 1] {
+            assert!(verify_accumulator_update(&update[..len], &guardians).is_err());
+        }
+    }
+}